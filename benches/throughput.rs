@@ -0,0 +1,52 @@
+//generation throughput at increasing sizes, run with `cargo bench --bench throughput`
+//sizes can be overridden with THROUGHPUT_SIZES, e.g. THROUGHPUT_SIZES=1000000,5000000
+//the default sizes go up to 100M queries and take a few minutes in total
+//cargo test builds and runs benches without --bench and in debug, so then only a small smoke size is run
+
+use query_generator::{DonePolicy, Generator};
+
+use rand::SeedableRng;
+use rand_pcg::Pcg64;
+use std::env;
+use std::io::prelude::*;
+use std::io;
+use std::time::Instant;
+
+const DEFAULT_SIZES: &str = "100000,1000000,10000000,100000000";
+const SMOKE_SIZES: &str = "1000";
+
+fn main() -> io::Result<()> {
+    let default_sizes = match env::args().any(|arg| arg == "--bench") {
+        true => DEFAULT_SIZES,
+        false => SMOKE_SIZES,
+    };
+    let sizes = env::var("THROUGHPUT_SIZES").unwrap_or_else(|_| default_sizes.to_owned());
+    let sizes: Vec<usize> = sizes.split(',')
+        .map(|size| size.trim().parse().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e)))
+        .collect::<io::Result<_>>()?;
+    let policies = [DonePolicy::Uniform, DonePolicy::Oldest, DonePolicy::Newest];
+
+    println!("{:>12} {:>8} {:>10} {:>14}", "queries", "policy", "seconds", "queries/sec");
+    for size in sizes {
+        for policy in policies.iter() {
+            let rng = &mut Pcg64::seed_from_u64(0);
+            //same parameters as the 5m benchmark file
+            let mut generator = Generator::builder()
+                .words(1000)
+                .tags(100)
+                .history(1000)
+                .done_policy(*policy)
+                .build(rng)
+                .unwrap();
+            let mut writer = io::BufWriter::new(io::sink());
+            let start = Instant::now();
+            for query in generator.queries(rng).take(size) {
+                writeln!(writer, "{}", query)?;
+            }
+            writer.flush()?;
+            let seconds = start.elapsed().as_secs_f64();
+            println!("{:>12} {:>8} {:>10.2} {:>14.0}", size, policy, seconds, size as f64 / seconds);
+        }
+    }
+    Ok(())
+}
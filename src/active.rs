@@ -0,0 +1,158 @@
+use rand::prelude::*;
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+//which active task a done query marks as done
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DonePolicy {
+    #[default]
+    Uniform,    //any active task, all equally likely
+    Oldest,     //the active task with the lowest id
+    Newest,     //the active task with the highest id
+}
+
+impl FromStr for DonePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniform" => Ok(DonePolicy::Uniform),
+            "oldest" => Ok(DonePolicy::Oldest),
+            "newest" => Ok(DonePolicy::Newest),
+            _ => Err(format!("unknown done policy `{}`, expected uniform, oldest or newest", s)),
+        }
+    }
+}
+
+impl fmt::Display for DonePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DonePolicy::Uniform => write!(f, "uniform"),
+            DonePolicy::Oldest => write!(f, "oldest"),
+            DonePolicy::Newest => write!(f, "newest"),
+        }
+    }
+}
+
+const INACTIVE: u32 = u32::MAX;
+
+//set of active ids supporting O(1) insertion, removal and uniform choice
+//ids are expected to be handed out densely from 0, as the generator does
+//oldest and newest choices keep an ordered copy of the set as well, making them O(log n)
+pub struct ActiveIds {
+    policy: DonePolicy,
+    ids: Vec<u64>,                  //every active id, in no particular order
+    positions: Vec<u32>,            //index into ids of every id handed out so far, or INACTIVE
+    ordered: Option<BTreeSet<u64>>, //only kept for the oldest and newest policies
+}
+
+impl ActiveIds {
+    pub fn new(policy: DonePolicy) -> Self {
+        ActiveIds {
+            policy,
+            ids: vec![],
+            positions: vec![],
+            ordered: match policy {
+                DonePolicy::Uniform => None,
+                DonePolicy::Oldest | DonePolicy::Newest => Some(BTreeSet::new()),
+            },
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn contains(&self, id: u64) -> bool {
+        self.positions.get(id as usize).is_some_and(|position| *position != INACTIVE)
+    }
+
+    pub fn insert(&mut self, id: u64) {
+        if self.contains(id) {
+            return;
+        }
+        let index = id as usize;
+        if index >= self.positions.len() {
+            self.positions.resize(index + 1, INACTIVE);
+        }
+        self.positions[index] = self.ids.len() as u32;
+        self.ids.push(id);
+        if let Some(ordered) = &mut self.ordered {
+            ordered.insert(id);
+        }
+    }
+
+    pub fn remove(&mut self, id: u64) -> bool {
+        if !self.contains(id) {
+            return false;
+        }
+        let position = self.positions[id as usize] as usize;
+        self.ids.swap_remove(position);
+        if let Some(moved) = self.ids.get(position) {
+            self.positions[*moved as usize] = position as u32;
+        }
+        self.positions[id as usize] = INACTIVE;
+        if let Some(ordered) = &mut self.ordered {
+            ordered.remove(&id);
+        }
+        true
+    }
+
+    //any active id, all equally likely, leaving it active
+    pub fn pick<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<u64> {
+        self.ids.choose(rng).copied()
+    }
+
+    //removes and returns an active id chosen by the done policy
+    pub fn take<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Option<u64> {
+        let id = match (self.policy, &self.ordered) {
+            (DonePolicy::Oldest, Some(ordered)) => *ordered.iter().next()?,
+            (DonePolicy::Newest, Some(ordered)) => *ordered.iter().next_back()?,
+            _ => *self.ids.choose(rng)?,
+        };
+        self.remove(id);
+        Some(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_pcg::Pcg64;
+
+    #[test]
+    fn active_ids_test() {
+        let rng = &mut Pcg64::seed_from_u64(0);
+        for policy in [DonePolicy::Uniform, DonePolicy::Oldest, DonePolicy::Newest].iter() {
+            let mut active = ActiveIds::new(*policy);
+            for id in 0..10 {
+                active.insert(id);
+            }
+            assert!(active.remove(4));
+            assert!(!active.remove(4));
+            assert!(!active.remove(10));
+            assert_eq!(active.len(), 9);
+            assert!(active.pick(rng).is_some_and(|id| active.contains(id)));
+            let mut taken = vec![];
+            while let Some(id) = active.take(rng) {
+                assert!(!active.contains(id));
+                taken.push(id);
+            }
+            match policy {
+                DonePolicy::Oldest => assert_eq!(taken, vec![0, 1, 2, 3, 5, 6, 7, 8, 9]),
+                DonePolicy::Newest => assert_eq!(taken, vec![9, 8, 7, 6, 5, 3, 2, 1, 0]),
+                DonePolicy::Uniform => {
+                    taken.sort_unstable();
+                    assert_eq!(taken, vec![0, 1, 2, 3, 5, 6, 7, 8, 9]);
+                },
+            }
+            assert!(active.is_empty());
+        }
+    }
+}
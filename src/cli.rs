@@ -0,0 +1,528 @@
+use query_generator::{Answers, DonePolicy, Folding, Format, Lengths, Matching, Popularity, QueryWeights, Script, SearchWeights, Selectivity};
+
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+pub const USAGE: &str = "\
+Generates task list queries for benchmarking and testing
+
+USAGE:
+    query-generator [OPTIONS]
+    query-generator expect --input <PATH> --expected <PATH> [--answers <ANSWERS>] [--matching <MATCHING>] [--folding <FOLDING>]
+    query-generator run [OPTIONS] [--input <PATH>] -- <TARGET> [ARGS...]
+    query-generator judge --input <PATH> [--matching <MATCHING>] [--folding <FOLDING>] [--time-limit <SECONDS>] [--memory-limit <MB>] -- <CANDIDATE> [ARGS...]
+
+OPTIONS:
+    -o, --output <PATH>      file to write the queries to, with what they were generated for written to <PATH>.meta
+                             [default: queries.txt]
+        --format <FORMAT>    text, jsonl for one json object per query and no count line,
+                             or sql for the tasks/task_words/task_tags schema then one line of sql per query [default: text]
+    -n, --queries <N>        number of queries to generate [default: 5000]
+    -w, --words <N>          number of distinct words to draw descriptions from [default: 100]
+    -t, --tags <N>           number of distinct tags to draw from [default: 50]
+        --history <N>        number of recent adds that searches are based on [default: 100]
+        --weights <LIST>     relative chance of each query kind: add, done, search, edit, tag, untag, undo and list
+                             [default: add=2,done=1,search=1, the rest 0]
+        --search-weights <LIST>
+                             relative chance of each kind of search: hit for terms from a recent add which is still active,
+                             random for random terms, completed for terms from a recent add which has been marked done,
+                             miss for terms checked to match no active task, boolean for an expression using | and -
+                             which matches a recent add that is still active [default: hit=3,random=1, the rest 0]
+        --selectivity <BAND> how many active tasks each search other than a miss or boolean should find: any, N, N-M or N+,
+                             where each bound is a number of tasks or a percentage of the active tasks like 5% [default: any]
+        --matching <MATCHING>
+                             how the task list being tested matches search terms against words and tags, which decides
+                             how terms are derived from them and how expected answers are worked out: exact, prefix,
+                             substring, subsequence, or typo:<budget> for the whole word with up to that many characters
+                             deleted, inserted, doubled, changed or swapped with the next, where typo is typo:1; terms
+                             derived from a word or tag are always exactly the budget away from it, which the metadata
+                             records as typo_distance [default: subsequence]
+        --folding <FOLDING>  whether the task list being tested matches regardless of case, accents, full-width forms
+                             and unicode normalisation: exact or folded, where folded searches also write terms
+                             in those variant forms [default: exact]
+        --variants <FRACTION>
+                             chance of each word and tag in adds, edits and tags, and each search term if folded,
+                             being written in mixed case, title case, full-width characters, nfd or nfc [default: 0]
+        --escapes <FRACTION> chance of each add and edit description having quotes, backslashes, hashes, tabs or extra
+                             spaces put in it, which the text format escapes as \\\", \\\\, \\n and \\r [default: 0]
+        --invalid <FRACTION> chance of each query being replaced by invalid input, which the reference answers with an error
+                             saying why: a done of an id never given out or already done, a negative or overflowing id,
+                             an unknown command, a missing quote, an empty search or a line cut short; jsonl and sql
+                             only get the dones and empty searches, which sql writes as comments [default: 0]
+        --done-policy <POLICY>
+                             which active task each done marks: uniform, oldest or newest [default: uniform]
+        --word-popularity <DIST>
+                             how often each word is drawn: uniform, zipf:<exponent> or hotspot:<items>:<draws>,
+                             e.g. hotspot:0.1:0.9 gives 10% of the words 90% of the draws [default: uniform]
+        --tag-popularity <DIST>
+                             how often each tag is drawn, as for --word-popularity [default: uniform]
+        --add-words <LIST>   relative chance of 0, 1, 2... extra words in each add beyond the first [default: 4,2,1,1]
+        --add-tags <LIST>    relative chance of 0, 1, 2... tags in each add [default: 4,2,1,1]
+        --search-words <LIST>
+                             relative chance of 0, 1, 2... extra words in each search beyond the first [default: 4,2,1,1]
+        --search-tags <LIST> relative chance of 0, 1, 2... tags in each search [default: 4,2,1,1]
+        --pool-segments <LIST>
+                             relative chance of 0, 1, 2... extra hyphenated segments in each word and tag [default: 4,2,1,1]
+        --pool-hyphens <LIST>
+                             relative chance of 0, 1, 2... extra hyphens between segments [default: 18,1,1]
+        --script <SCRIPT>    built-in segments and alphabet to build words, tags and search terms from: ascii for english
+                             words and a-z, latin for accented latin, cyrillic, cjk or emoji [default: ascii]
+        --tag-script <SCRIPT>
+                             built-in segments and alphabet to build tags and their search terms from instead,
+                             e.g. emoji for emoji tags on ascii words [default: the same as words]
+        --segment-file <PATH>
+                             file with one segment per line to build words and tags from instead of the script's segments
+        --alphabet-file <PATH>
+                             file with one character per line to build random search terms from instead of the script's
+                             alphabet, where a character is anything displayed as one, like an accented letter or an emoji
+        --seed <N>           seed for the random number generator, printed when not given
+        --expected <PATH>    also write the answer a correct task list gives to every query; every search and list answer
+                             holds all of its ids, so the file grows with the square of the number of queries, around 10 GB
+                             for a million queries with the settings of the 5m example below, see --answers
+        --answers <ANSWERS>  what --expected writes for searches and lists: full for the number of matches then their
+                             ids, or count for only the number, which keeps the file about as big as the queries [default: full]
+    -h, --help               print this message
+
+COMMANDS:
+    expect                   answer an existing query file with the reference task list
+        -i, --input <PATH>       text query file to answer
+        --expected <PATH>        file to write the answers to
+        --answers <ANSWERS>      full or count, as for generating a file [default: full]
+        --matching <MATCHING>    how search terms match [default: from <PATH>.meta if there is one, otherwise subsequence]
+        --folding <FOLDING>      whether they ignore case and accents [default: from <PATH>.meta if there is one, otherwise exact]
+    run                      stream queries into a target program's stdin and report its latency,
+                             the target must answer each query with one line on stdout and flush it;
+                             takes the same options as generating a file, apart from --output, --format and --expected
+        -i, --input <PATH>       send the queries in an existing text file instead of generating them
+        -- <TARGET> [ARGS...]    the program to run and its arguments
+    judge                    run a candidate on a query file and check its answers against the reference task list,
+                             the candidate reads the whole file on stdin and writes one line per query on stdout
+        -i, --input <PATH>       text query file to give the candidate
+        --matching <MATCHING>    how search terms match, as for expect
+        --folding <FOLDING>      whether they ignore case and accents, as for expect
+        --time-limit <SECONDS>   wall clock time the candidate may take
+        --memory-limit <MB>      peak resident memory the candidate may use, linux only
+        -- <CANDIDATE> [ARGS...] the program to judge and its arguments
+
+EXAMPLES (don't forget to use release build):
+    query-generator -o benchmark_5m.txt -n 5000000 -w 1000 -t 100 --history 1000
+    query-generator -o benchmark_5k.txt -n 5000 -w 100 -t 50 --history 100 --seed 42
+    query-generator run -n 100000 --seed 42 -- ./target/release/task-manager
+";
+
+pub enum Command {
+    Generate(Box<GenerateOptions>),
+    Expect(ExpectOptions),
+    Run(Box<RunOptions>),
+    Judge(JudgeOptions),
+    Help,
+}
+
+pub struct GenerateOptions {
+    pub output: PathBuf,
+    pub format: Format,
+    pub query_num: usize,
+    pub word_num: usize,
+    pub tag_num: usize,
+    pub add_query_history_num: usize,
+    pub weights: QueryWeights,
+    pub search_weights: SearchWeights,
+    pub selectivity: Selectivity,
+    pub matching: Matching,
+    pub folding: Folding,
+    pub variants: f64,
+    pub escapes: f64,
+    pub invalid: f64,
+    pub lengths: Lengths,
+    pub done_policy: DonePolicy,
+    pub word_popularity: Popularity,
+    pub tag_popularity: Popularity,
+    pub script: Script,
+    pub tag_script: Option<Script>,
+    pub segment_file: Option<PathBuf>,
+    pub alphabet_file: Option<PathBuf>,
+    pub seed: Option<u64>,
+    pub expected: Option<PathBuf>,
+    pub answers: Answers,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        GenerateOptions {
+            output: PathBuf::from("queries.txt"),
+            format: Default::default(),
+            query_num: 5000,
+            word_num: 100,
+            tag_num: 50,
+            add_query_history_num: 100,
+            weights: Default::default(),
+            search_weights: Default::default(),
+            selectivity: Default::default(),
+            matching: Default::default(),
+            folding: Default::default(),
+            variants: 0.0,
+            escapes: 0.0,
+            invalid: 0.0,
+            lengths: Default::default(),
+            done_policy: Default::default(),
+            word_popularity: Default::default(),
+            tag_popularity: Default::default(),
+            script: Default::default(),
+            tag_script: None,
+            segment_file: None,
+            alphabet_file: None,
+            seed: None,
+            expected: None,
+            answers: Default::default(),
+        }
+    }
+}
+
+pub struct ExpectOptions {
+    pub input: PathBuf,
+    pub expected: PathBuf,
+    pub answers: Answers,
+    pub matching: Option<Matching>, //None to use the input's metadata
+    pub folding: Option<Folding>,   //None to use the input's metadata
+}
+
+pub struct RunOptions {
+    pub generate: GenerateOptions,  //only the options which affect the queries themselves are used
+    pub input: Option<PathBuf>,
+    pub target: Vec<String>,
+}
+
+pub struct JudgeOptions {
+    pub input: PathBuf,
+    pub matching: Option<Matching>, //None to use the input's metadata
+    pub folding: Option<Folding>,   //None to use the input's metadata
+    pub time_limit: Option<Duration>,
+    pub memory_limit: Option<u64>,  //in bytes
+    pub candidate: Vec<String>,
+}
+
+//args should not include the program name
+pub fn parse_args<I: Iterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut args = Args::new(args);
+    match args.peek() {
+        Some("expect") => {
+            args.next();
+            parse_expect(args)
+        },
+        Some("run") => {
+            args.next();
+            parse_run(args)
+        },
+        Some("judge") => {
+            args.next();
+            parse_judge(args)
+        },
+        _ => parse_generate(args),
+    }
+}
+
+fn parse_generate<I: Iterator<Item = String>>(mut args: Args<I>) -> Result<Command, String> {
+    let mut options = GenerateOptions::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-o" | "--output" => options.output = args.value(&arg)?,
+            "--format" => options.format = args.value(&arg)?,
+            "--expected" => options.expected = Some(args.value(&arg)?),
+            "--answers" => options.answers = args.value(&arg)?,
+            _ => generator_option(&mut options, &arg, &mut args)?,
+        }
+    }
+    validate(&options)?;
+    Ok(Command::Generate(Box::new(options)))
+}
+
+fn parse_run<I: Iterator<Item = String>>(mut args: Args<I>) -> Result<Command, String> {
+    let mut options = RunOptions {
+        generate: GenerateOptions::default(),
+        input: None,
+        target: vec![],
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-i" | "--input" => options.input = Some(args.value(&arg)?),
+            "--" => {
+                options.target = args.rest();
+                break;
+            },
+            _ => generator_option(&mut options.generate, &arg, &mut args)?,
+        }
+    }
+    if options.target.is_empty() {
+        return Err("run needs a target program after --".to_owned());
+    }
+    validate(&options.generate)?;
+    Ok(Command::Run(Box::new(options)))
+}
+
+fn parse_judge<I: Iterator<Item = String>>(mut args: Args<I>) -> Result<Command, String> {
+    let mut input = None;
+    let mut matching = None;
+    let mut folding = None;
+    let mut time_limit = None;
+    let mut memory_limit = None;
+    let mut candidate = vec![];
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-i" | "--input" => input = Some(args.value(&arg)?),
+            "--matching" => matching = Some(args.value(&arg)?),
+            "--folding" => folding = Some(args.value(&arg)?),
+            "--time-limit" => {
+                let seconds: f64 = args.value(&arg)?;
+                if !seconds.is_finite() || seconds <= 0.0 {
+                    return Err("--time-limit must be a positive number of seconds".to_owned());
+                }
+                time_limit = Some(Duration::from_secs_f64(seconds));
+            },
+            "--memory-limit" => {
+                let megabytes: u64 = args.value(&arg)?;
+                memory_limit = Some(megabytes.checked_mul(1024 * 1024).ok_or("--memory-limit is too large")?);
+            },
+            "--" => {
+                candidate = args.rest();
+                break;
+            },
+            _ => return Err(unexpected(&arg)),
+        }
+    }
+    if candidate.is_empty() {
+        return Err("judge needs a candidate program after --".to_owned());
+    }
+    Ok(Command::Judge(JudgeOptions {
+        input: input.ok_or("judge needs --input")?,
+        matching,
+        folding,
+        time_limit,
+        memory_limit,
+        candidate,
+    }))
+}
+
+//options which affect the queries generated, shared by generating a file and running a target
+fn generator_option<I: Iterator<Item = String>>(options: &mut GenerateOptions, arg: &str, args: &mut Args<I>) -> Result<(), String> {
+    match arg {
+        "-n" | "--queries" => options.query_num = args.value(arg)?,
+        "-w" | "--words" => options.word_num = args.value(arg)?,
+        "-t" | "--tags" => options.tag_num = args.value(arg)?,
+        "--history" => options.add_query_history_num = args.value(arg)?,
+        "--weights" => options.weights = args.value(arg)?,
+        "--search-weights" => options.search_weights = args.value(arg)?,
+        "--selectivity" => options.selectivity = args.value(arg)?,
+        "--matching" => options.matching = args.value(arg)?,
+        "--folding" => options.folding = args.value(arg)?,
+        "--variants" => options.variants = args.value(arg)?,
+        "--escapes" => options.escapes = args.value(arg)?,
+        "--invalid" => options.invalid = args.value(arg)?,
+        "--done-policy" => options.done_policy = args.value(arg)?,
+        "--add-words" => options.lengths.add_words = args.value(arg)?,
+        "--add-tags" => options.lengths.add_tags = args.value(arg)?,
+        "--search-words" => options.lengths.search_words = args.value(arg)?,
+        "--search-tags" => options.lengths.search_tags = args.value(arg)?,
+        "--pool-segments" => options.lengths.pool_segments = args.value(arg)?,
+        "--pool-hyphens" => options.lengths.pool_hyphens = args.value(arg)?,
+        "--word-popularity" => options.word_popularity = args.value(arg)?,
+        "--tag-popularity" => options.tag_popularity = args.value(arg)?,
+        "--script" => options.script = args.value(arg)?,
+        "--tag-script" => options.tag_script = Some(args.value(arg)?),
+        "--segment-file" => options.segment_file = Some(args.value(arg)?),
+        "--alphabet-file" => options.alphabet_file = Some(args.value(arg)?),
+        "--seed" => options.seed = Some(args.value(arg)?),
+        _ => return Err(unexpected(arg)),
+    }
+    Ok(())
+}
+
+fn validate(options: &GenerateOptions) -> Result<(), String> {
+    if options.word_num == 0 {
+        return Err("--words must be at least 1".to_owned());
+    }
+    if options.tag_num == 0 {
+        return Err("--tags must be at least 1".to_owned());
+    }
+    if options.add_query_history_num == 0 {
+        return Err("--history must be at least 1".to_owned());
+    }
+    if options.weights.is_zero() {
+        return Err("--weights must give at least one query kind a non-zero weight".to_owned());
+    }
+    if options.search_weights.is_zero() {
+        return Err("--search-weights must give at least one search kind a non-zero weight".to_owned());
+    }
+    if !(0.0..=1.0).contains(&options.variants) {
+        return Err("--variants must be between 0 and 1".to_owned());
+    }
+    if !(0.0..=1.0).contains(&options.escapes) {
+        return Err("--escapes must be between 0 and 1".to_owned());
+    }
+    if !(0.0..=1.0).contains(&options.invalid) {
+        return Err("--invalid must be between 0 and 1".to_owned());
+    }
+    Ok(())
+}
+
+fn parse_expect<I: Iterator<Item = String>>(mut args: Args<I>) -> Result<Command, String> {
+    let mut input = None;
+    let mut expected = None;
+    let mut answers = Default::default();
+    let mut matching = None;
+    let mut folding = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-i" | "--input" => input = Some(args.value(&arg)?),
+            "--expected" => expected = Some(args.value(&arg)?),
+            "--answers" => answers = args.value(&arg)?,
+            "--matching" => matching = Some(args.value(&arg)?),
+            "--folding" => folding = Some(args.value(&arg)?),
+            _ => return Err(unexpected(&arg)),
+        }
+    }
+    Ok(Command::Expect(ExpectOptions {
+        input: input.ok_or("expect needs --input")?,
+        expected: expected.ok_or("expect needs --expected")?,
+        answers,
+        matching,
+        folding,
+    }))
+}
+
+fn unexpected(arg: &str) -> String {
+    format!("unexpected argument `{}`", arg)
+}
+
+//splits "--name=value" into two arguments so both spellings are accepted
+struct Args<I: Iterator<Item = String>> {
+    args: I,
+    pending: Option<String>,
+    peeked: Option<String>,
+}
+
+impl<I: Iterator<Item = String>> Args<I> {
+    fn new(args: I) -> Self {
+        Args { args, pending: None, peeked: None }
+    }
+
+    fn peek(&mut self) -> Option<&str> {
+        if self.peeked.is_none() {
+            self.peeked = self.next();
+        }
+        self.peeked.as_deref()
+    }
+
+    fn next(&mut self) -> Option<String> {
+        if let Some(arg) = self.peeked.take() {
+            return Some(arg);
+        }
+        if let Some(value) = self.pending.take() {
+            return Some(value);
+        }
+        let arg = self.args.next()?;
+        if arg.starts_with("--") {
+            if let Some((name, value)) = arg.split_once('=') {
+                self.pending = Some(value.to_owned());
+                return Some(name.to_owned());
+            }
+        }
+        Some(arg)
+    }
+
+    //everything left, taken as is
+    fn rest(&mut self) -> Vec<String> {
+        let mut rest = vec![];
+        while let Some(arg) = self.peeked.take().or_else(|| self.pending.take()).or_else(|| self.args.next()) {
+            rest.push(arg);
+        }
+        rest
+    }
+
+    fn value<T: FromStr>(&mut self, name: &str) -> Result<T, String> where T::Err: fmt::Display {
+        let value = self.next().ok_or(format!("{} needs a value", name))?;
+        value.parse().map_err(|e| format!("invalid value `{}` for {}: {}", value, name, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parse_args_test() {
+        match parse(&["-o", "out.txt", "--queries=10", "--words", "5", "--seed", "3", "--weights", "search=4", "--add-tags", "0,0,1", "--done-policy", "oldest", "--tag-popularity", "zipf:1.2", "--format", "jsonl", "--matching", "prefix", "--tag-script", "emoji", "--folding", "folded", "--variants", "0.2"]) {
+            Ok(Command::Generate(options)) => {
+                assert_eq!(options.output, PathBuf::from("out.txt"));
+                assert_eq!(options.query_num, 10);
+                assert_eq!(options.format, Format::Jsonl);
+                assert_eq!(options.word_num, 5);
+                assert_eq!(options.tag_num, 50);
+                assert_eq!(options.seed, Some(3));
+                assert_eq!(options.weights.search, 4);
+                assert_eq!(options.lengths.add_tags.to_string(), "0,0,1");
+                assert_eq!(options.lengths.add_words.to_string(), "4,2,1,1");
+                assert_eq!(options.done_policy, DonePolicy::Oldest);
+                assert_eq!(options.word_popularity, Popularity::Uniform);
+                assert_eq!(options.tag_popularity, Popularity::Zipf(1.2));
+                assert_eq!(options.matching, Matching::Prefix);
+                assert_eq!((options.folding, options.variants), (Folding::Folded, 0.2));
+                assert_eq!((options.script, options.tag_script), (Script::Ascii, Some(Script::Emoji)));
+            },
+            _ => panic!("expected generate options"),
+        }
+        assert!(matches!(parse(&["expect", "-i", "a.txt", "--expected", "b.txt"]), Ok(Command::Expect(ExpectOptions { answers: Answers::Full, .. }))));
+        assert!(matches!(parse(&["expect", "-i", "a.txt", "--expected", "b.txt", "--answers", "count"]), Ok(Command::Expect(ExpectOptions { answers: Answers::Count, .. }))));
+        assert!(matches!(parse(&["--expected", "b.txt", "--answers=count"]), Ok(Command::Generate(options)) if options.answers == Answers::Count));
+        assert!(parse(&["--answers", "ids"]).is_err());
+        assert!(matches!(parse(&["--words", "3", "--help"]), Ok(Command::Help)));
+        match parse(&["run", "-n", "7", "--", "./target", "--threads=2", "-n", "1"]) {
+            Ok(Command::Run(options)) => {
+                assert_eq!(options.generate.query_num, 7);
+                assert_eq!(options.input, None);
+                assert_eq!(options.target, vec!["./target", "--threads=2", "-n", "1"]);
+            },
+            _ => panic!("expected run options"),
+        }
+
+        assert!(parse(&["--words", "0"]).is_err());
+        assert!(parse(&["--tags", "0"]).is_err());
+        assert!(parse(&["--queries", "-1"]).is_err());
+        assert!(parse(&["--queries"]).is_err());
+        assert!(parse(&["--weights", "add=0,done=0,search=0"]).is_err());
+        assert!(parse(&["--search-weights", "hit=0,random=0"]).is_err());
+        assert!(parse(&["--selectivity", "10-1"]).is_err());
+        assert!(parse(&["--pool-hyphens", "0"]).is_err());
+        assert!(parse(&["--done-policy", "random"]).is_err());
+        assert!(parse(&["--variants", "2"]).is_err());
+        assert!(parse(&["--escapes", "-0.5"]).is_err());
+        assert!(parse(&["--invalid", "0.1", "--format", "sql"]).is_ok());
+        assert!(parse(&["--invalid", "0.1"]).is_ok());
+        assert!(parse(&["--folding", "lower"]).is_err());
+        assert!(parse(&["--matching", "fuzzy"]).is_err());
+        assert!(parse(&["--script", "greek"]).is_err());
+        assert!(parse(&["out.txt"]).is_err());
+        assert!(parse(&["expect", "-i", "a.txt"]).is_err());
+        match parse(&["judge", "-i", "in.txt", "--time-limit", "1.5", "--memory-limit", "256", "--", "./solution"]) {
+            Ok(Command::Judge(options)) => {
+                assert_eq!(options.time_limit, Some(Duration::from_millis(1500)));
+                assert_eq!(options.memory_limit, Some(256 * 1024 * 1024));
+                assert_eq!(options.candidate, vec!["./solution"]);
+            },
+            _ => panic!("expected judge options"),
+        }
+        assert!(parse(&["run", "-n", "7"]).is_err());
+        assert!(parse(&["judge", "--", "./solution"]).is_err());
+        assert!(parse(&["judge", "-i", "in.txt", "--time-limit", "0", "--", "./solution"]).is_err());
+        assert!(parse(&["run", "-o", "out.txt", "--", "./target"]).is_err());
+    }
+}
@@ -1029,4 +1029,654 @@ pub const SEGMENTS: [&str; 1000] = [
    "teeth",
    "shell",
    "neck"
-];
\ No newline at end of file
+];
+
+pub const LATIN_SEGMENTS: [&str; 86] = [
+    "café",
+    "crème",
+    "brûlée",
+    "naïve",
+    "façade",
+    "élan",
+    "déjà",
+    "über",
+    "straße",
+    "größe",
+    "müde",
+    "schön",
+    "fräulein",
+    "jäger",
+    "zürich",
+    "köln",
+    "mañana",
+    "niño",
+    "año",
+    "señor",
+    "piñata",
+    "jalapeño",
+    "acción",
+    "corazón",
+    "canción",
+    "pingüino",
+    "ação",
+    "coração",
+    "pão",
+    "irmã",
+    "avó",
+    "limão",
+    "smörgås",
+    "fjärd",
+    "sjö",
+    "ö",
+    "kärlek",
+    "bröd",
+    "øl",
+    "kjøkken",
+    "blåbær",
+    "ærlig",
+    "æble",
+    "søster",
+    "ísland",
+    "þorp",
+    "örn",
+    "żółw",
+    "źdźbło",
+    "łódź",
+    "gęś",
+    "świeca",
+    "część",
+    "kraków",
+    "ćma",
+    "ńa",
+    "čaj",
+    "řeka",
+    "šťastný",
+    "žlutý",
+    "kůň",
+    "město",
+    "dům",
+    "úsměv",
+    "ďábel",
+    "ağaç",
+    "çiçek",
+    "göz",
+    "şehir",
+    "ılık",
+    "üzüm",
+    "ışık",
+    "phở",
+    "bánh-mì",
+    "việt",
+    "đường",
+    "hà-nội",
+    "crêpe",
+    "hôtel",
+    "forêt",
+    "île",
+    "où",
+    "garçon",
+    "noël",
+    "maître",
+    "pâté",
+];
+
+pub const LATIN_ALPHABET: [&str; 81] = [
+    "a",
+    "b",
+    "c",
+    "d",
+    "e",
+    "f",
+    "g",
+    "h",
+    "i",
+    "j",
+    "k",
+    "l",
+    "m",
+    "n",
+    "o",
+    "p",
+    "q",
+    "r",
+    "s",
+    "t",
+    "u",
+    "v",
+    "w",
+    "x",
+    "y",
+    "z",
+    "à",
+    "á",
+    "â",
+    "ã",
+    "ä",
+    "å",
+    "æ",
+    "ç",
+    "è",
+    "é",
+    "ê",
+    "ë",
+    "ì",
+    "í",
+    "î",
+    "ï",
+    "ñ",
+    "ò",
+    "ó",
+    "ô",
+    "õ",
+    "ö",
+    "ø",
+    "œ",
+    "ù",
+    "ú",
+    "û",
+    "ü",
+    "ý",
+    "ÿ",
+    "ß",
+    "ą",
+    "ć",
+    "ę",
+    "ł",
+    "ń",
+    "ś",
+    "ź",
+    "ż",
+    "č",
+    "ď",
+    "ě",
+    "ň",
+    "ř",
+    "š",
+    "ť",
+    "ů",
+    "ž",
+    "ğ",
+    "ı",
+    "ş",
+    "đ",
+    "ơ",
+    "ư",
+    "-",
+];
+
+pub const CYRILLIC_SEGMENTS: [&str; 76] = [
+    "дом",
+    "кот",
+    "молоко",
+    "хлеб",
+    "вода",
+    "работа",
+    "письмо",
+    "книга",
+    "окно",
+    "дверь",
+    "стол",
+    "стул",
+    "город",
+    "улица",
+    "машина",
+    "поезд",
+    "небо",
+    "солнце",
+    "луна",
+    "звезда",
+    "море",
+    "река",
+    "лес",
+    "поле",
+    "гора",
+    "снег",
+    "дождь",
+    "ветер",
+    "огонь",
+    "земля",
+    "друг",
+    "семья",
+    "мама",
+    "папа",
+    "брат",
+    "сестра",
+    "утро",
+    "вечер",
+    "ночь",
+    "день",
+    "неделя",
+    "месяц",
+    "год",
+    "час",
+    "время",
+    "школа",
+    "урок",
+    "слово",
+    "язык",
+    "музыка",
+    "песня",
+    "чай",
+    "кофе",
+    "сахар",
+    "соль",
+    "мясо",
+    "рыба",
+    "яблоко",
+    "ёлка",
+    "ёж",
+    "мёд",
+    "съезд",
+    "объект",
+    "щука",
+    "жёлтый",
+    "чёрный",
+    "белый",
+    "зелёный",
+    "синий",
+    "красный",
+    "їжак",
+    "ґанок",
+    "єдність",
+    "київ",
+    "село",
+    "хата",
+];
+
+pub const CYRILLIC_ALPHABET: [&str; 38] = [
+    "а",
+    "б",
+    "в",
+    "г",
+    "д",
+    "е",
+    "ё",
+    "ж",
+    "з",
+    "и",
+    "й",
+    "к",
+    "л",
+    "м",
+    "н",
+    "о",
+    "п",
+    "р",
+    "с",
+    "т",
+    "у",
+    "ф",
+    "х",
+    "ц",
+    "ч",
+    "ш",
+    "щ",
+    "ъ",
+    "ы",
+    "ь",
+    "э",
+    "ю",
+    "я",
+    "і",
+    "ї",
+    "є",
+    "ґ",
+    "-",
+];
+
+pub const CJK_SEGMENTS: [&str; 80] = [
+    "東京",
+    "大阪",
+    "京都",
+    "猫",
+    "犬",
+    "鳥",
+    "花",
+    "山",
+    "川",
+    "海",
+    "空",
+    "雨",
+    "雪",
+    "風",
+    "火",
+    "水",
+    "木",
+    "金",
+    "土",
+    "日",
+    "月",
+    "星",
+    "人",
+    "子",
+    "学生",
+    "先生",
+    "学校",
+    "会社",
+    "電車",
+    "自転車",
+    "新聞",
+    "手紙",
+    "仕事",
+    "時間",
+    "天気",
+    "買い物",
+    "料理",
+    "牛乳",
+    "お茶",
+    "ご飯",
+    "パン",
+    "野菜",
+    "果物",
+    "りんご",
+    "みかん",
+    "さくら",
+    "ありがとう",
+    "こんにちは",
+    "さようなら",
+    "ひらがな",
+    "カタカナ",
+    "コーヒー",
+    "テスト",
+    "メール",
+    "北京",
+    "上海",
+    "中国",
+    "你好",
+    "谢谢",
+    "朋友",
+    "工作",
+    "电脑",
+    "手机",
+    "图书馆",
+    "咖啡",
+    "茶",
+    "米饭",
+    "面条",
+    "서울",
+    "한국",
+    "사랑",
+    "감사",
+    "학교",
+    "친구",
+    "커피",
+    "우유",
+    "빵",
+    "김치",
+    "바다",
+    "하늘",
+];
+
+pub const CJK_ALPHABET: [&str; 110] = [
+    "日",
+    "月",
+    "山",
+    "川",
+    "水",
+    "火",
+    "木",
+    "金",
+    "土",
+    "人",
+    "口",
+    "大",
+    "小",
+    "中",
+    "上",
+    "下",
+    "東",
+    "西",
+    "南",
+    "北",
+    "天",
+    "田",
+    "力",
+    "女",
+    "子",
+    "学",
+    "生",
+    "先",
+    "年",
+    "本",
+    "見",
+    "行",
+    "出",
+    "入",
+    "気",
+    "電",
+    "車",
+    "語",
+    "国",
+    "時",
+    "分",
+    "長",
+    "高",
+    "安",
+    "新",
+    "古",
+    "白",
+    "黒",
+    "赤",
+    "青",
+    "猫",
+    "犬",
+    "鳥",
+    "花",
+    "雨",
+    "雪",
+    "あ",
+    "い",
+    "う",
+    "え",
+    "お",
+    "か",
+    "き",
+    "く",
+    "け",
+    "こ",
+    "さ",
+    "し",
+    "す",
+    "せ",
+    "そ",
+    "た",
+    "ち",
+    "つ",
+    "て",
+    "と",
+    "ア",
+    "イ",
+    "ウ",
+    "エ",
+    "オ",
+    "カ",
+    "キ",
+    "ク",
+    "ケ",
+    "コ",
+    "你",
+    "好",
+    "谢",
+    "朋",
+    "友",
+    "工",
+    "作",
+    "电",
+    "脑",
+    "가",
+    "나",
+    "다",
+    "라",
+    "마",
+    "바",
+    "사",
+    "아",
+    "자",
+    "차",
+    "카",
+    "타",
+    "파",
+    "하",
+    "-",
+];
+
+pub const EMOJI_SEGMENTS: [&str; 77] = [
+    "😀",
+    "😂",
+    "🥲",
+    "😍",
+    "🤔",
+    "😴",
+    "🤯",
+    "🥳",
+    "😎",
+    "🤖",
+    "👻",
+    "💩",
+    "🔥",
+    "✨",
+    "🌟",
+    "⭐",
+    "⚡",
+    "🌈",
+    "☀️",
+    "🌧️",
+    "❄️",
+    "🌊",
+    "🌲",
+    "🌸",
+    "🍎",
+    "🍕",
+    "🍣",
+    "☕",
+    "🍺",
+    "🎂",
+    "🎉",
+    "🎁",
+    "🎵",
+    "📚",
+    "📝",
+    "📌",
+    "📎",
+    "💼",
+    "💻",
+    "📱",
+    "🔑",
+    "🔒",
+    "🏠",
+    "🏢",
+    "🚗",
+    "🚲",
+    "✈️",
+    "🚀",
+    "⚽",
+    "🏀",
+    "🎮",
+    "🧩",
+    "❤️",
+    "💔",
+    "✅",
+    "❌",
+    "⚠️",
+    "❓",
+    "💯",
+    "👍🏽",
+    "👋🏿",
+    "🙏🏻",
+    "💪🏾",
+    "👩‍💻",
+    "👨‍🍳",
+    "🧑‍🚀",
+    "👨‍👩‍👧",
+    "🏳️‍🌈",
+    "🐈‍⬛",
+    "❤️‍🔥",
+    "🇬🇧",
+    "🇯🇵",
+    "🇺🇦",
+    "🇧🇷",
+    "🇫🇷",
+    "1️⃣",
+    "2️⃣",
+];
+
+pub const EMOJI_ALPHABET: [&str; 78] = [
+    "😀",
+    "😂",
+    "🥲",
+    "😍",
+    "🤔",
+    "😴",
+    "🤯",
+    "🥳",
+    "😎",
+    "🤖",
+    "👻",
+    "💩",
+    "🔥",
+    "✨",
+    "🌟",
+    "⭐",
+    "⚡",
+    "🌈",
+    "☀️",
+    "🌧️",
+    "❄️",
+    "🌊",
+    "🌲",
+    "🌸",
+    "🍎",
+    "🍕",
+    "🍣",
+    "☕",
+    "🍺",
+    "🎂",
+    "🎉",
+    "🎁",
+    "🎵",
+    "📚",
+    "📝",
+    "📌",
+    "📎",
+    "💼",
+    "💻",
+    "📱",
+    "🔑",
+    "🔒",
+    "🏠",
+    "🏢",
+    "🚗",
+    "🚲",
+    "✈️",
+    "🚀",
+    "⚽",
+    "🏀",
+    "🎮",
+    "🧩",
+    "❤️",
+    "💔",
+    "✅",
+    "❌",
+    "⚠️",
+    "❓",
+    "💯",
+    "👍🏽",
+    "👋🏿",
+    "🙏🏻",
+    "💪🏾",
+    "👩‍💻",
+    "👨‍🍳",
+    "🧑‍🚀",
+    "👨‍👩‍👧",
+    "🏳️‍🌈",
+    "🐈‍⬛",
+    "❤️‍🔥",
+    "🇬🇧",
+    "🇯🇵",
+    "🇺🇦",
+    "🇧🇷",
+    "🇫🇷",
+    "1️⃣",
+    "2️⃣",
+    "-",
+];
@@ -0,0 +1,337 @@
+use rand::prelude::*;
+
+use std::fmt;
+use std::str::FromStr;
+
+//discrete distribution over the counts 0, 1, 2, ... given the relative weight of each count
+//e.g. "4,2,1,1" gives 0 half the time, 1 a quarter of the time and 2 or 3 an eighth of the time each
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counts {
+    weights: Vec<u32>,
+    total: u32,
+}
+
+impl Counts {
+    pub fn new(weights: Vec<u32>) -> Result<Self, String> {
+        let total = weights.iter().try_fold(0u32, |total, weight| total.checked_add(*weight))
+            .ok_or("weights are too large")?;
+        if total == 0 {
+            return Err("at least one count needs a non-zero weight".to_owned());
+        }
+        Ok(Counts { weights, total })
+    }
+
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        let mut roll = rng.gen_range(0..self.total);
+        for (count, weight) in self.weights.iter().enumerate() {
+            if roll < *weight {
+                return count;
+            }
+            roll -= weight;
+        }
+        Default::default() //unreachable
+    }
+}
+
+impl FromStr for Counts {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let weights = s.split(',')
+            .map(|weight| weight.trim().parse().map_err(|_| format!("invalid weight `{}`", weight)))
+            .collect::<Result<Vec<u32>, String>>()?;
+        Counts::new(weights)
+    }
+}
+
+impl fmt::Display for Counts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let weights: Vec<String> = self.weights.iter().map(|weight| weight.to_string()).collect();
+        write!(f, "{}", weights.join(","))
+    }
+}
+
+//how many of each part go into the queries and pool entries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lengths {
+    pub add_words: Counts,          //words in each add beyond the first
+    pub add_tags: Counts,           //tags in each add
+    pub search_words: Counts,       //words in each search beyond the first
+    pub search_tags: Counts,        //tags in each search
+    pub pool_segments: Counts,      //segments in each pool entry beyond the first
+    pub pool_hyphens: Counts,       //hyphens joining segments beyond the first
+}
+
+impl Default for Lengths {
+    fn default() -> Self {
+        let extras = Counts::new(vec![4, 2, 1, 1]).unwrap();   //50%, 25%, 12.5%, 12.5%
+        Lengths {
+            add_words: extras.clone(),
+            add_tags: extras.clone(),
+            search_words: extras.clone(),
+            search_tags: extras.clone(),
+            pool_segments: extras,
+            pool_hyphens: Counts::new(vec![18, 1, 1]).unwrap(), //90%, 5%, 5%
+        }
+    }
+}
+
+//how often each entry in the word or tag pool is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Popularity {
+    #[default]
+    Uniform,
+    Zipf(f64),                              //the nth most popular entry is drawn in proportion to 1/n^exponent
+    Hotspot { items: f64, draws: f64 },     //a fraction of the entries get a fraction of the draws
+}
+
+//parses "uniform", "zipf:<exponent>" or "hotspot:<fraction of items>:<fraction of draws>"
+impl FromStr for Popularity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let number = |part: &str| part.parse::<f64>().map_err(|_| format!("invalid number `{}`", part));
+        let popularity = match parts.as_slice() {
+            ["uniform"] => Popularity::Uniform,
+            ["zipf", exponent] => Popularity::Zipf(number(exponent)?),
+            ["hotspot", items, draws] => Popularity::Hotspot { items: number(items)?, draws: number(draws)? },
+            _ => return Err(format!("unknown popularity `{}`, expected uniform, zipf:<exponent> or hotspot:<items>:<draws>", s)),
+        };
+        popularity.validate()?;
+        Ok(popularity)
+    }
+}
+
+impl fmt::Display for Popularity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Popularity::Uniform => write!(f, "uniform"),
+            Popularity::Zipf(exponent) => write!(f, "zipf:{}", exponent),
+            Popularity::Hotspot { items, draws } => write!(f, "hotspot:{}:{}", items, draws),
+        }
+    }
+}
+
+impl Popularity {
+    pub fn validate(&self) -> Result<(), String> {
+        match *self {
+            Popularity::Uniform => Ok(()),
+            Popularity::Zipf(exponent) if exponent.is_finite() && exponent >= 0.0 => Ok(()),
+            Popularity::Zipf(exponent) => Err(format!("zipf exponent {} must be zero or more", exponent)),
+            Popularity::Hotspot { items, draws } if items > 0.0 && items <= 1.0 && (0.0..=1.0).contains(&draws) => Ok(()),
+            Popularity::Hotspot { .. } => Err("hotspot fractions must be between 0 and 1".to_owned()),
+        }
+    }
+}
+
+//how many active tasks each search should find
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Selectivity {
+    #[default]
+    Any,
+    Between(Bound, Option<Bound>),  //at least the first bound and at most the second, with no upper limit if there isn't one
+}
+
+//a number of tasks, or a fraction of the active tasks at the time of the search
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bound {
+    Count(usize),
+    Fraction(f64),
+}
+
+impl Selectivity {
+    //smallest and largest number of matches allowed when there are the given number of active tasks
+    //fractions round inwards, so a band can be empty when there are few active tasks
+    pub fn range(&self, active: usize) -> (usize, usize) {
+        match *self {
+            Selectivity::Any => (0, usize::MAX),
+            Selectivity::Between(min, max) => {
+                let min = match min {
+                    Bound::Count(count) => count,
+                    Bound::Fraction(fraction) => (fraction * active as f64).ceil() as usize,
+                };
+                let max = match max {
+                    Some(Bound::Count(count)) => count,
+                    Some(Bound::Fraction(fraction)) => (fraction * active as f64).floor() as usize,
+                    None => usize::MAX,
+                };
+                (min, max)
+            },
+        }
+    }
+}
+
+//parses "any", "N", "N-M" or "N+", where each bound is a number of tasks or a percentage of the active tasks like "5%"
+impl FromStr for Selectivity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bound = |part: &str| match part.strip_suffix('%') {
+            Some(percent) => match percent.parse::<f64>() {
+                Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(Bound::Fraction(percent / 100.0)),
+                _ => Err(format!("invalid percentage `{}`, expected 0% to 100%", part)),
+            },
+            None => part.parse().map(Bound::Count).map_err(|_| format!("invalid number of tasks `{}`", part)),
+        };
+        if s == "any" {
+            return Ok(Selectivity::Any);
+        }
+        let (min, max) = if let Some(min) = s.strip_suffix('+') {
+            (bound(min)?, None)
+        }
+        else if let Some((min, max)) = s.split_once('-') {
+            (bound(min)?, Some(bound(max)?))
+        }
+        else {
+            (bound(s)?, Some(bound(s)?))
+        };
+        match (min, max) {
+            (Bound::Count(min), Some(Bound::Count(max))) if min > max => Err(format!("empty band `{}`", s)),
+            (Bound::Fraction(min), Some(Bound::Fraction(max))) if min > max => Err(format!("empty band `{}`", s)),
+            _ => Ok(Selectivity::Between(min, max)),
+        }
+    }
+}
+
+impl fmt::Display for Selectivity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Selectivity::Any => write!(f, "any"),
+            Selectivity::Between(min, None) => write!(f, "{}+", min),
+            Selectivity::Between(min, Some(max)) if min == max => write!(f, "{}", min),
+            Selectivity::Between(min, Some(max)) => write!(f, "{}-{}", min, max),
+        }
+    }
+}
+
+impl fmt::Display for Bound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Bound::Count(count) => write!(f, "{}", count),
+            Bound::Fraction(fraction) => write!(f, "{}%", fraction * 100.0),
+        }
+    }
+}
+
+//draws indices into a pool of a fixed size according to a popularity
+#[derive(Debug, Clone)]
+pub struct Picker {
+    num: usize,
+    kind: PickerKind,
+}
+
+#[derive(Debug, Clone)]
+enum PickerKind {
+    Uniform,
+    Cumulative(Vec<f64>),               //running total of each index's share of the draws
+    Hotspot { hot: usize, draws: f64 }, //indices below hot get the given fraction of the draws
+}
+
+impl Picker {
+    pub fn new(popularity: &Popularity, num: usize) -> Self {
+        let kind = match *popularity {
+            Popularity::Uniform => PickerKind::Uniform,
+            Popularity::Zipf(exponent) => {
+                let mut total = 0.0;
+                let mut cumulative: Vec<f64> = (1..=num)
+                    .map(|rank| {
+                        total += 1.0 / (rank as f64).powf(exponent);
+                        total
+                    })
+                    .collect();
+                for share in cumulative.iter_mut() {
+                    *share /= total;
+                }
+                PickerKind::Cumulative(cumulative)
+            },
+            Popularity::Hotspot { items, draws } => {
+                let hot = ((items * num as f64).ceil() as usize).clamp(1, num.max(1));
+                PickerKind::Hotspot { hot, draws }
+            },
+        };
+        Picker { num, kind }
+    }
+
+    pub fn pick<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        match &self.kind {
+            PickerKind::Uniform => rng.gen_range(0..self.num),
+            PickerKind::Cumulative(cumulative) => {
+                let roll: f64 = rng.gen();
+                cumulative.partition_point(|share| *share <= roll).min(self.num - 1)
+            },
+            PickerKind::Hotspot { hot, draws } => {
+                if *hot == self.num || rng.gen_bool(*draws) {
+                    rng.gen_range(0..*hot)
+                }
+                else {
+                    rng.gen_range(*hot..self.num)
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_pcg::Pcg64;
+
+    #[test]
+    fn counts_test() {
+        assert_eq!("4,2,1,1".parse::<Counts>().unwrap().to_string(), "4,2,1,1");
+        assert!("0,0".parse::<Counts>().is_err());
+        assert!("1,x".parse::<Counts>().is_err());
+        assert!("4294967295,1".parse::<Counts>().is_err());
+
+        let counts: Counts = "0,0,3,0,1,0".parse().unwrap();
+        let rng = &mut Pcg64::seed_from_u64(0);
+        let mut seen = [0; 6];
+        for _ in 0..4000 {
+            seen[counts.sample(rng)] += 1;
+        }
+        assert_eq!((seen[0], seen[1], seen[3], seen[5]), (0, 0, 0, 0));
+        assert!(seen[2] > 2 * seen[4]);
+    }
+
+    #[test]
+    fn popularity_test() {
+        assert_eq!("uniform".parse(), Ok(Popularity::Uniform));
+        assert_eq!("zipf:1.5".parse(), Ok(Popularity::Zipf(1.5)));
+        assert_eq!("hotspot:0.1:0.9".parse(), Ok(Popularity::Hotspot { items: 0.1, draws: 0.9 }));
+        assert!("zipf".parse::<Popularity>().is_err());
+        assert!("zipf:-1".parse::<Popularity>().is_err());
+        assert!("hotspot:0:0.5".parse::<Popularity>().is_err());
+        assert!("hotspot:0.5:2".parse::<Popularity>().is_err());
+
+        let rng = &mut Pcg64::seed_from_u64(0);
+        let draws = |popularity: &str, rng: &mut Pcg64| {
+            let picker = Picker::new(&popularity.parse().unwrap(), 100);
+            let mut seen = vec![0; 100];
+            for _ in 0..10000 {
+                seen[picker.pick(rng)] += 1;
+            }
+            seen
+        };
+        let zipf = draws("zipf:1", rng);
+        assert!(zipf[0] > zipf[1] && zipf[1] > zipf[9] && zipf[9] > zipf[99]);
+        let hotspot = draws("hotspot:0.1:0.9", rng);
+        let hot: usize = hotspot[..10].iter().sum();
+        assert!(hot > 8500 && hot < 9500);
+    }
+
+    #[test]
+    fn selectivity_test() {
+        for band in ["any", "1", "2-10", "100+", "5%-10%", "50%+"].iter() {
+            assert_eq!(band.parse::<Selectivity>().unwrap().to_string(), *band);
+        }
+        assert!("10-2".parse::<Selectivity>().is_err());
+        assert!("150%".parse::<Selectivity>().is_err());
+        assert!("few".parse::<Selectivity>().is_err());
+
+        assert_eq!(Selectivity::Any.range(10), (0, usize::MAX));
+        assert_eq!("2-10".parse::<Selectivity>().unwrap().range(1000), (2, 10));
+        assert_eq!("100+".parse::<Selectivity>().unwrap().range(10), (100, usize::MAX));
+        assert_eq!("5%-10%".parse::<Selectivity>().unwrap().range(55), (3, 5));
+    }
+}
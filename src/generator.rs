@@ -1,137 +1,989 @@
 use crate::types::*;
-use crate::constants::*;
-use pool::*;
+use crate::vocabulary::*;
+use crate::distribution::*;
+use crate::active::*;
+use crate::oracle::TaskList;
+use crate::matching::{within_distance, Folding, Matching};
+pub use pool::Pool;
 
 use std::cmp;
+use std::str::FromStr;
 use std::collections::VecDeque;
 use rand::prelude::*;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+//relative chance of generating each kind of query
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryWeights {
+    pub add: u32,
+    pub done: u32,
+    pub search: u32,
+    pub edit: u32,
+    pub tag: u32,
+    pub untag: u32,
+    pub undo: u32,
+    pub list: u32,
+}
+
+impl Default for QueryWeights {
+    fn default() -> Self {
+        QueryWeights {
+            add: 2,     //50%
+            done: 1,    //25%
+            search: 1,  //25%
+            edit: 0,
+            tag: 0,
+            untag: 0,
+            undo: 0,
+            list: 0,
+        }
+    }
+}
+
+impl QueryWeights {
+    pub fn is_zero(&self) -> bool {
+        self.add == 0 && self.done == 0 && self.search == 0 && self.edit == 0 && self.tag == 0 && self.untag == 0 && self.undo == 0 && self.list == 0
+    }
+}
+
+//parses a comma separated list like "add=2,done=1,search=1", kinds which aren't mentioned keep their default weight
+impl FromStr for QueryWeights {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = QueryWeights::default();
+        for (kind, weight) in parse_weights(s)? {
+            match kind {
+                "add" => weights.add = weight,
+                "done" => weights.done = weight,
+                "search" => weights.search = weight,
+                "edit" => weights.edit = weight,
+                "tag" => weights.tag = weight,
+                "untag" => weights.untag = weight,
+                "undo" => weights.undo = weight,
+                "list" => weights.list = weight,
+                _ => return Err(format!("unknown query kind `{}`", kind)),
+            }
+        }
+        Ok(weights)
+    }
+}
+
+//relative chance of each way of building a search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchWeights {
+    pub hit: u32,       //matches a recently added task which is still active
+    pub random: u32,    //random terms from the alphabet, which may or may not match anything
+    pub completed: u32, //matches a recently added task which has been marked done, so never returns that task
+    pub miss: u32,      //checked to match no active task at all when it's generated
+    pub boolean: u32,   //an expression using or and not, built from recent adds, which matches one that's still active
+}
+
+impl Default for SearchWeights {
+    fn default() -> Self {
+        SearchWeights {
+            hit: 3,         //75%
+            random: 1,      //25%
+            completed: 0,   //0%
+            miss: 0,        //0%
+            boolean: 0,     //0%
+        }
+    }
+}
+
+impl SearchWeights {
+    pub fn is_zero(&self) -> bool {
+        self.hit == 0 && self.random == 0 && self.completed == 0 && self.miss == 0 && self.boolean == 0
+    }
+}
+
+//parses a comma separated list like "hit=3,random=1,miss=1", as for QueryWeights
+impl FromStr for SearchWeights {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = SearchWeights::default();
+        for (kind, weight) in parse_weights(s)? {
+            match kind {
+                "hit" => weights.hit = weight,
+                "random" => weights.random = weight,
+                "completed" => weights.completed = weight,
+                "miss" => weights.miss = weight,
+                "boolean" => weights.boolean = weight,
+                _ => return Err(format!("unknown search kind `{}`", kind)),
+            }
+        }
+        Ok(weights)
+    }
+}
+
+fn parse_weights(s: &str) -> Result<Vec<(&str, u32)>, String> {
+    s.split(',')
+        .map(|pair| {
+            let (kind, weight) = pair.split_once('=').ok_or(format!("expected kind=weight, found `{}`", pair))?;
+            let weight = weight.parse().map_err(|_| format!("invalid weight `{}` for {}", weight, kind))?;
+            Ok((kind, weight))
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy)]
+enum QueryKind {
+    Add,
+    Done,
+    Search,
+    Edit,
+    Tag,
+    Untag,
+    Undo,
+    List,
+}
+
+#[derive(Clone, Copy)]
+enum SearchKind {
+    Hit,
+    Random,
+    Completed,
+    Miss,
+    Boolean,
+}
+
+//the ways of writing invalid input, each of which the reference answers with an error
+#[derive(Clone, Copy)]
+enum InvalidKind {
+    NeverIssued,    //a done of an id no add has been given yet
+    DoneTwice,      //a done of a task already marked done
+    Negative,       //a negative id
+    Overflowing,    //an id too big for 64 bits
+    UnknownCommand,
+    MissingQuote,   //an add with the opening or closing quote of its description left out
+    EmptySearch,    //a search with no terms
+    Truncated,      //an add or search cut off partway through
+}
+
+const INVALID_KINDS: [InvalidKind; 8] = [
+    InvalidKind::NeverIssued,
+    InvalidKind::DoneTwice,
+    InvalidKind::Negative,
+    InvalidKind::Overflowing,
+    InvalidKind::UnknownCommand,
+    InvalidKind::MissingQuote,
+    InvalidKind::EmptySearch,
+    InvalidKind::Truncated,
+];
+
+//the kinds which are still queries, for formats which can't write a line that doesn't parse
+const WELL_FORMED_KINDS: [InvalidKind; 3] = [InvalidKind::NeverIssued, InvalidKind::DoneTwice, InvalidKind::EmptySearch];
+
+//misspelt, miscapitalised or made up commands, "" gives a line starting with a space
+const UNKNOWN_COMMANDS: [&str; 10] = ["", "remove", "delete", "complete", "find", "ad", "Add", "DONE", "serach", "lists"];
+
+//how many cuts are tried for a truncated line before settling for one ending just after the opening quote of an add
+const TRUNCATE_ATTEMPTS: usize = 16;
+
+//how many times typos are made before settling for doubling the last grapheme to get the distance right
+const TYPO_ATTEMPTS: usize = 16;
+
+//how many searches are tried for a miss before falling back to a term too long to match anything
+const MISS_ATTEMPTS: usize = 16;
+//how many searches are started from when looking for one with the right number of matches
+const SELECTIVITY_ATTEMPTS: usize = 16;
+
+//configures and builds a Generator, anything not set keeps the same default as the command line
+#[derive(Debug, Clone)]
+pub struct GeneratorBuilder {
+    word_num: usize,
+    tag_num: usize,
+    add_query_history_num: usize,
+    weights: QueryWeights,
+    search_weights: SearchWeights,
+    selectivity: Selectivity,
+    matching: Matching,
+    folding: Folding,
+    variants: f64,
+    escapes: f64,
+    invalid: f64,
+    malformed: bool,
+    lengths: Lengths,
+    done_policy: DonePolicy,
+    vocabulary: Vocabulary,
+    word_popularity: Popularity,
+    tag_popularity: Popularity,
+}
+
+impl Default for GeneratorBuilder {
+    fn default() -> Self {
+        GeneratorBuilder {
+            word_num: 100,
+            tag_num: 50,
+            add_query_history_num: 100,
+            weights: Default::default(),
+            search_weights: Default::default(),
+            selectivity: Default::default(),
+            matching: Default::default(),
+            folding: Default::default(),
+            variants: 0.0,
+            escapes: 0.0,
+            invalid: 0.0,
+            malformed: true,
+            lengths: Default::default(),
+            done_policy: Default::default(),
+            vocabulary: Default::default(),
+            word_popularity: Default::default(),
+            tag_popularity: Default::default(),
+        }
+    }
+}
+
+impl GeneratorBuilder {
+    //number of distinct words to draw descriptions from
+    pub fn words(mut self, word_num: usize) -> Self {
+        self.word_num = word_num;
+        self
+    }
+
+    //number of distinct tags to draw from
+    pub fn tags(mut self, tag_num: usize) -> Self {
+        self.tag_num = tag_num;
+        self
+    }
+
+    //number of recent adds that searches are based on
+    pub fn history(mut self, add_query_history_num: usize) -> Self {
+        self.add_query_history_num = add_query_history_num;
+        self
+    }
+
+    pub fn weights(mut self, weights: QueryWeights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    //how each search is built
+    pub fn search_weights(mut self, search_weights: SearchWeights) -> Self {
+        self.search_weights = search_weights;
+        self
+    }
+
+    //how many active tasks each search should find, miss searches always find none and boolean searches aren't checked
+    pub fn selectivity(mut self, selectivity: Selectivity) -> Self {
+        self.selectivity = selectivity;
+        self
+    }
+
+    //how the task list being tested matches search terms, which decides how terms are derived from task words and tags
+    pub fn matching(mut self, matching: Matching) -> Self {
+        self.matching = matching;
+        self
+    }
+
+    //whether the task list being tested ignores case, accents, full-width forms and normalisation
+    //when it does, search terms are written in variant forms as well as words and tags
+    pub fn folding(mut self, folding: Folding) -> Self {
+        self.folding = folding;
+        self
+    }
+
+    //chance of each word and tag, and each search term if folding, being written in mixed case, title case,
+    //full-width characters, nfd or nfc rather than as it is in the pool
+    pub fn variants(mut self, variants: f64) -> Self {
+        self.variants = variants;
+        self
+    }
+
+    //chance of each description having quotes, backslashes, hashes, tabs or extra spaces put in it as words of their own,
+    //some wrapped around or in front of a word, which searches never use as terms
+    pub fn escapes(mut self, escapes: f64) -> Self {
+        self.escapes = escapes;
+        self
+    }
+
+    //chance of each query being replaced by invalid input: a done of an id which was never given out or is already done,
+    //a negative or overflowing id, an unknown command, a missing quote, an empty search or a truncated line
+    //done and search queries answer with the usual error, anything which doesn't parse is a Query::Invalid
+    pub fn invalid(mut self, invalid: f64) -> Self {
+        self.invalid = invalid;
+        self
+    }
+
+    //whether invalid input includes lines which don't parse, which only the text format can write
+    //without them it's only dones of inactive ids and empty searches, which sql writes as comments
+    //as QueryWriter does for every query the reference rejects, so sql files don't exercise a database's error handling
+    pub fn malformed(mut self, malformed: bool) -> Self {
+        self.malformed = malformed;
+        self
+    }
+
+    //how many words, tags, segments and hyphens go into each query and pool entry
+    pub fn lengths(mut self, lengths: Lengths) -> Self {
+        self.lengths = lengths;
+        self
+    }
+
+    //which active task each done query marks as done
+    pub fn done_policy(mut self, done_policy: DonePolicy) -> Self {
+        self.done_policy = done_policy;
+        self
+    }
+
+    //segments and alphabet to build words, tags and random search terms from, tags use its tag vocabulary if it has one
+    pub fn vocabulary(mut self, vocabulary: Vocabulary) -> Self {
+        self.vocabulary = vocabulary;
+        self
+    }
+
+    //how often each word in the pool is drawn
+    pub fn word_popularity(mut self, popularity: Popularity) -> Self {
+        self.word_popularity = popularity;
+        self
+    }
+
+    //how often each tag in the pool is drawn
+    pub fn tag_popularity(mut self, popularity: Popularity) -> Self {
+        self.tag_popularity = popularity;
+        self
+    }
+
+    //the rng is only used to fill the word and tag pools, queries are drawn with whichever rng is passed to get_query
+    pub fn build<R: Rng + ?Sized>(self, rng: &mut R) -> Result<Generator, String> {
+        if self.word_num == 0 {
+            return Err("the word pool needs at least one word".to_owned());
+        }
+        if self.tag_num == 0 {
+            return Err("the tag pool needs at least one tag".to_owned());
+        }
+        if self.add_query_history_num == 0 {
+            return Err("the add query history needs room for at least one query".to_owned());
+        }
+        self.word_popularity.validate()?;
+        self.tag_popularity.validate()?;
+        let weights = self.weights;
+        if weights.is_zero() {
+            return Err("at least one query kind needs a non-zero weight".to_owned());
+        }
+        let search_weights = self.search_weights;
+        if search_weights.is_zero() {
+            return Err("at least one search kind needs a non-zero weight".to_owned());
+        }
+        if !(0.0..=1.0).contains(&self.variants) {
+            return Err("the chance of variants has to be between 0 and 1".to_owned());
+        }
+        if !(0.0..=1.0).contains(&self.escapes) {
+            return Err("the chance of escapes has to be between 0 and 1".to_owned());
+        }
+        if !(0.0..=1.0).contains(&self.invalid) {
+            return Err("the chance of invalid input has to be between 0 and 1".to_owned());
+        }
+        let pool = Pool::new(rng, self.word_num, self.tag_num, &self.lengths, &self.vocabulary)
+            .with_popularity(&self.word_popularity, &self.tag_popularity);
+        Ok(Generator {
+            next_id: 0,
+            active_ids: ActiveIds::new(self.done_policy),
+            tasks: match search_weights.miss > 0 || self.selectivity != Selectivity::Any {
+                true => Some(TaskList::with_matching(self.matching).with_folding(self.folding).with_undo(weights.undo > 0)),
+                false => None,
+            },
+            //a word wrapped in quotes is two longer than any in the pool
+            miss_length: pool.longest() + self.matching.slack() + if self.escapes > 0.0 { 2 } else { 0 },
+            pool,
+            weights,
+            search_weights,
+            selectivity: self.selectivity,
+            matching: self.matching,
+            folding: self.folding,
+            variants: self.variants,
+            escapes: self.escapes,
+            invalid: self.invalid,
+            malformed: self.malformed,
+            lengths: self.lengths,
+            vocabulary: self.vocabulary,
+            add_query_history_num: self.add_query_history_num,
+            add_query_history: VecDeque::with_capacity(self.add_query_history_num),
+            live_history_num: 0,
+            done_history: VecDeque::with_capacity(self.add_query_history_num),
+            done_ids: if weights.undo > 0 { Some(vec![]) } else { None },
+        })
+    }
+}
 
 pub struct Generator {
     next_id: u64,
-    active_ids: Vec<u64>,
+    active_ids: ActiveIds,
+    tasks: Option<TaskList>,    //every active task, only kept when searches have to be checked against them
+    miss_length: usize,         //length of a search term too long to match any word or tag
     pool: Pool,
+    weights: QueryWeights,
+    search_weights: SearchWeights,
+    selectivity: Selectivity,
+    matching: Matching,
+    folding: Folding,
+    variants: f64,
+    escapes: f64,
+    invalid: f64,
+    malformed: bool,    //whether invalid input can be lines which don't parse
+    lengths: Lengths,
+    vocabulary: Vocabulary,
     add_query_history_num: usize,
-    add_query_history: VecDeque<Query>,
+    add_query_history: VecDeque<Option<Query>>,    //the most recent adds in id order, None once the task is marked done
+    live_history_num: usize,                        //how many of those are still active
+    done_history: VecDeque<(u64, Query)>,           //adds from the history which have since been marked done, with their ids
+    done_ids: Option<Vec<u64>>,                     //every id marked done and not reopened, most recent last, only kept for undos
 }
 impl Generator {
-    pub fn new(rng: &mut ThreadRng, word_num: usize, tag_num: usize, add_query_history_num: usize) -> Self {
-        Generator {
-            next_id: 0,
-            active_ids: vec![],
-            pool: Pool::new(rng, word_num, tag_num),
-            add_query_history_num,
-            add_query_history: VecDeque::with_capacity(add_query_history_num),
+    pub fn builder() -> GeneratorBuilder {
+        Default::default()
+    }
+
+    //endless stream of queries, use take to get a fixed number
+    pub fn queries<'a, R: Rng + ?Sized>(&'a mut self, rng: &'a mut R) -> Queries<'a, R> {
+        Queries { generator: self, rng }
+    }
+
+    pub fn matching(&self) -> Matching {
+        self.matching
+    }
+
+    pub fn get_query<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Query {
+        let query = match self.invalid > 0.0 && rng.gen_bool(self.invalid) {
+            true => self.invalid_query(rng),
+            false => self.next_query(rng),
+        };
+        if let Some(tasks) = &mut self.tasks {
+            tasks.apply(&query);
         }
+        query
     }
 
-    pub fn get_query(&mut self, rng: &mut ThreadRng) -> Query {
-        loop {
-            match rng.gen_range::<u8, _>(1..=4) {
-                1..=2 => {                          //50%
-                    let query = generate_add(rng, &self.pool);
-                    self.active_ids.push(self.next_id);
-                    self.next_id = self.next_id + 1;
-                    if self.add_query_history.len() == self.add_query_history_num { //maintain maximum size of queries by removing oldest element
-                        self.add_query_history.pop_front();
+    fn next_query<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Query {
+        match self.choose_kind(rng) {
+            QueryKind::Add => {
+                let mut words = generate_words(rng, &self.pool, &self.lengths);
+                let mut tags = generate_tags(rng, &self.pool, &self.lengths);
+                self.vary(rng, &mut words);
+                self.vary(rng, &mut tags);
+                self.escape(rng, &mut words);
+                let query = Query::Add(words, tags);
+                self.active_ids.insert(self.next_id);
+                self.next_id += 1;
+                if self.add_query_history.len() == self.add_query_history_num { //maintain maximum size of queries by removing oldest element
+                    if let Some(Some(_)) = self.add_query_history.pop_front() {
+                        self.live_history_num -= 1;
                     }
-                    self.add_query_history.push_back(query.clone());
-                    return query;
-                },
-                3 => {                              //25%
-                    if self.active_ids.len() > 0 { //cannot done if there are no active tasks
-                        let index = rng.gen_range(0..self.active_ids.len());
-                        let query = generate_done(self.active_ids[index]);
-                        self.active_ids.remove(index);
-                        return query;
+                }
+                self.add_query_history.push_back(Some(query.clone()));
+                self.live_history_num += 1;
+                query
+            },
+            QueryKind::Done => {
+                let id = self.active_ids.take(rng).unwrap();
+                self.retire(id);
+                if let Some(done_ids) = &mut self.done_ids {
+                    done_ids.push(id);
+                }
+                generate_done(id)
+            },
+            QueryKind::Edit => {
+                let id = self.active_ids.pick(rng).unwrap();
+                let mut words = generate_words(rng, &self.pool, &self.lengths);
+                self.vary(rng, &mut words);
+                self.escape(rng, &mut words);
+                if let Some(Query::Add(history_words, _)) = self.history_entry(id) {
+                    *history_words = words.clone();
+                }
+                Query::Edit(id, words)
+            },
+            QueryKind::Untag if self.live_history_num > 0 => {
+                //only recent adds have their tags to hand, if none of them have any this becomes a tag instead
+                let first_id = self.next_id - self.add_query_history.len() as u64;
+                let has_tags = |query: &Query| matches!(query, Query::Add(_, tags) if !tags.is_empty());
+                match self.live_history_index(rng, has_tags) {
+                    Some(index) => {
+                        let tags = match &mut self.add_query_history[index] {
+                            Some(Query::Add(_, tags)) => tags,
+                            _ => unreachable!(),
+                        };
+                        let tag = tags[rng.gen_range(0..tags.len())].clone();
+                        //the task list removes every tag which folds to the same thing
+                        let folding = self.folding;
+                        let folded = folding.fold(&tag).into_owned();
+                        tags.retain(|other| folding.fold(other) != folded);
+                        Query::Untag(first_id + index as u64, vec![tag])
+                    },
+                    None => self.tag_query(rng),
+                }
+            },
+            QueryKind::Tag | QueryKind::Untag => self.tag_query(rng),
+            QueryKind::Undo => {
+                let id = self.done_ids.as_mut().and_then(Vec::pop).unwrap();
+                self.reopen(id);
+                Query::Undo
+            },
+            QueryKind::List => Query::List,
+            QueryKind::Search => {
+                let kind = choose(rng, &self.search_kinds()).unwrap();
+                let mut search = match (kind, self.selectivity) {
+                    (SearchKind::Miss, _) | (SearchKind::Boolean, _) | (_, Selectivity::Any) => self.build_search(rng, kind),
+                    _ => self.targeted_search(rng, kind),
+                };
+                //folding makes no difference to what a variant finds, otherwise terms have to be written the same way as the task
+                if self.folding == Folding::Folded {
+                    match &mut search {
+                        Query::Search(terms) => self.vary_terms(rng, terms),
+                        Query::BooleanSearch(expr) => self.vary_expr(rng, expr),
+                        _ => (),
                     }
-                },
-                4 => {                              //25%
-                    if self.add_query_history.len() > 0 {
-                        let add_query = self.add_query_history.get(rng.gen_range(0..self.add_query_history.len())).unwrap();
-                        let query = generate_search(rng, add_query);
-                        return query;
+                }
+                search
+            },
+        }
+    }
+
+    fn build_search<R: Rng + ?Sized>(&self, rng: &mut R, kind: SearchKind) -> Query {
+        match kind {
+            //a task with nothing to search for gets a random search instead
+            SearchKind::Hit | SearchKind::Completed => {
+                let add_query = match kind {
+                    SearchKind::Hit => self.live_add_query(rng),
+                    _ => self.completed_add_query(rng),
+                };
+                match add_query {
+                    Some(add_query) => guaranteed_search(rng, add_query, &self.lengths, &self.vocabulary, self.matching),
+                    None => random_search(rng, &self.lengths, &self.vocabulary),
+                }
+            },
+            SearchKind::Random => random_search(rng, &self.lengths, &self.vocabulary),
+            SearchKind::Miss => self.miss_search(rng),
+            SearchKind::Boolean => {
+                let target = match self.live_add_query(rng) {
+                    Some(target) => target,
+                    None => return random_search(rng, &self.lengths, &self.vocabulary),
+                };
+                let others: Vec<&Query> = (0..2)
+                    .filter_map(|_| self.add_query_history[rng.gen_range(0..self.add_query_history.len())].as_ref())
+                    .filter(|other| has_terms(other))
+                    .collect();
+                boolean_search(rng, target, &others, &self.lengths, &self.vocabulary, self.matching, self.folding).into_query()
+            },
+        }
+    }
+
+    //kinds which can't currently be generated are left out and the rest keep their relative weights
+    //if nothing else is possible an add is generated regardless of its weight
+    fn choose_kind<R: Rng + ?Sized>(&self, rng: &mut R) -> QueryKind {
+        let can_search = !self.add_query_history.is_empty() && self.search_kinds().iter().any(|(_, weight)| *weight > 0);
+        let kinds = [
+            (QueryKind::Add, self.weights.add),
+            (QueryKind::Done, if self.active_ids.is_empty() { 0 } else { self.weights.done }), //cannot done if there are no active tasks
+            (QueryKind::Search, if can_search { self.weights.search } else { 0 }),             //cannot search without an add to base it on
+            (QueryKind::Edit, if self.active_ids.is_empty() { 0 } else { self.weights.edit }),
+            (QueryKind::Tag, if self.active_ids.is_empty() { 0 } else { self.weights.tag }),
+            (QueryKind::Untag, if self.active_ids.is_empty() { 0 } else { self.weights.untag }),
+            (QueryKind::Undo, if self.done_ids.as_ref().is_some_and(|ids| !ids.is_empty()) { self.weights.undo } else { 0 }),
+            (QueryKind::List, self.weights.list),
+        ];
+        choose(rng, &kinds).unwrap_or(QueryKind::Add)
+    }
+
+    //as for query kinds, searches which can't currently be built are left out
+    fn search_kinds(&self) -> [(SearchKind, u32); 5] {
+        [
+            (SearchKind::Hit, if self.live_history_num == 0 { 0 } else { self.search_weights.hit }),
+            (SearchKind::Random, self.search_weights.random),
+            (SearchKind::Completed, if self.search_weights.completed > 0 && self.can_complete() { self.search_weights.completed } else { 0 }),
+            (SearchKind::Miss, self.search_weights.miss),
+            (SearchKind::Boolean, if self.live_history_num == 0 { 0 } else { self.search_weights.boolean }),
+        ]
+    }
+
+    //a search of the given kind whose number of matches falls within the selectivity
+    //searches which find too much are narrowed to every word and tag of the task they came from, if they came from one,
+    //then searches which find too little are broadened a character or term at a time, each still matching everything it did before
+    //if nothing fits, for instance when the band needs more tasks than are active, the closest search found is used
+    fn targeted_search<R: Rng + ?Sized>(&self, rng: &mut R, kind: SearchKind) -> Query {
+        let tasks = self.tasks.as_ref().unwrap();
+        let (min, max) = self.selectivity.range(self.active_ids.len());
+        let mut closest: Option<(usize, Vec<WordOrTag>)> = None;
+        for _ in 0..SELECTIVITY_ATTEMPTS {
+            let mut add_query = match kind {
+                SearchKind::Hit => self.live_add_query(rng),
+                SearchKind::Completed => self.completed_add_query(rng),
+                SearchKind::Random | SearchKind::Miss | SearchKind::Boolean => None,
+            };
+            let search = match add_query {
+                Some(add_query) => guaranteed_search(rng, add_query, &self.lengths, &self.vocabulary, self.matching),
+                None => random_search(rng, &self.lengths, &self.vocabulary),
+            };
+            let mut terms = match search {
+                Query::Search(terms) => terms,
+                _ => unreachable!(),
+            };
+            loop {
+                let found = tasks.search(&terms).len();
+                let distance = if found < min { min - found } else { found.saturating_sub(max) };
+                if distance == 0 {
+                    return Query::Search(terms);
+                }
+                if closest.as_ref().is_none_or(|(closest, _)| distance < *closest) {
+                    closest = Some((distance, terms.clone()));
+                }
+                if found > max {
+                    match add_query.take() {
+                        Some(add_query) => terms = exact_search(rng, add_query, &self.vocabulary, self.matching),
+                        None => break,
                     }
+                }
+                else if !broaden(rng, &mut terms, self.matching) {
+                    break;
+                }
+            }
+        }
+        Query::Search(closest.unwrap().1)
+    }
+
+    //a search which no active task matches, checked against the live task list
+    //near misses, a hit with one extra character, are tried as well as random searches since they're the likeliest to trip up a fuzzy matcher
+    fn miss_search<R: Rng + ?Sized>(&self, rng: &mut R) -> Query {
+        let tasks = self.tasks.as_ref().unwrap();
+        for _ in 0..MISS_ATTEMPTS {
+            let add_query = match self.live_history_num > 0 && rng.gen() {
+                true => self.live_add_query(rng),
+                false => None,
+            };
+            let search = match add_query {
+                Some(add_query) => {
+                    let hit = guaranteed_search(rng, add_query, &self.lengths, &self.vocabulary, self.matching);
+                    near_miss(rng, hit, &self.vocabulary)
                 },
-                _ => return Default::default(),     //0%
+                None => random_search(rng, &self.lengths, &self.vocabulary),
             };
+            if let Query::Search(terms) = &search {
+                if tasks.search(terms).is_empty() {
+                    return search;
+                }
+            }
+        }
+        let term = random_term(rng, self.vocabulary.alphabet(), self.miss_length);
+        Query::Search(vec![WordOrTag::Word(term)])
+    }
+
+    //input which the reference answers with an error, leaving every task as it was
+    fn invalid_query<R: Rng + ?Sized>(&self, rng: &mut R) -> Query {
+        let kinds: &[InvalidKind] = if self.malformed { &INVALID_KINDS } else { &WELL_FORMED_KINDS };
+        let line = match *kinds.choose(rng).unwrap() {
+            InvalidKind::DoneTwice if !self.done_history.is_empty() => {
+                return Query::Done(self.done_history[rng.gen_range(0..self.done_history.len())].0);
+            },
+            InvalidKind::NeverIssued | InvalidKind::DoneTwice => {
+                return Query::Done(match rng.gen() {
+                    true => self.next_id + rng.gen_range(0..self.add_query_history_num as u64),
+                    false => u64::MAX,
+                });
+            },
+            InvalidKind::EmptySearch => return Query::Search(vec![]),
+            InvalidKind::Negative => {
+                let id = self.active_ids.pick(rng).unwrap_or(0).max(1);
+                self.id_line(rng, &format!("-{}", id))
+            },
+            InvalidKind::Overflowing => {
+                let id = u64::MAX as u128 + 1 + rng.gen_range(0..u64::MAX as u128);
+                self.id_line(rng, &id.to_string())
+            },
+            InvalidKind::UnknownCommand => {
+                let command = UNKNOWN_COMMANDS.choose(rng).unwrap();
+                let line = self.add_line(rng);
+                match (command.is_empty(), rng.gen()) {
+                    (false, true) => command.to_string(),
+                    _ => format!("{}{}", command, &line[line.find(' ').unwrap()..]),
+                }
+            },
+            InvalidKind::MissingQuote => {
+                let mut line = self.add_line(rng);
+                let (open, close) = (line.find('"').unwrap(), line.rfind('"').unwrap());
+                match rng.gen_range(0..3) {
+                    0 => {
+                        line.remove(close);
+                    },
+                    1 => {
+                        line.remove(open);
+                    },
+                    _ => {
+                        line.remove(close);
+                        line.remove(open);
+                    },
+                }
+                line
+            },
+            InvalidKind::Truncated => {
+                let line = match rng.gen() {
+                    true => self.add_line(rng),
+                    false => random_search(rng, &self.lengths, &self.vocabulary).to_string(),
+                };
+                let cuts: Vec<usize> = line.char_indices().map(|(i, _)| i).skip(1).collect();
+                (0..TRUNCATE_ATTEMPTS)
+                    .map(|_| &line[..*cuts.choose(rng).unwrap()])
+                    .find(|cut| cut.parse::<Query>().is_err())
+                    .unwrap_or("add \"")
+                    .to_owned()
+            },
+        };
+        let error = line.parse::<Query>().expect_err("invalid lines never parse").message;
+        Query::Invalid(line, error)
+    }
+
+    //a fresh add, written out, which changes nothing
+    fn add_line<R: Rng + ?Sized>(&self, rng: &mut R) -> String {
+        let mut words = generate_words(rng, &self.pool, &self.lengths);
+        self.escape(rng, &mut words);
+        Query::Add(words, generate_tags(rng, &self.pool, &self.lengths)).to_string()
+    }
+
+    //a done, edit, tag or untag written with the given id
+    fn id_line<R: Rng + ?Sized>(&self, rng: &mut R, id: &str) -> String {
+        match rng.gen_range(0..4) {
+            0 => format!("done {}", id),
+            1 => format!("edit {} \"{}\"", id, self.pool.get_word(rng)),
+            2 => format!("tag {} #{}", id, self.pool.get_tag(rng)),
+            _ => format!("untag {} #{}", id, self.pool.get_tag(rng)),
+        }
+    }
+
+    //moves a task which has just been marked done from the add history to the done history, if it's recent enough to be there
+    fn retire(&mut self, id: u64) {
+        let first_id = self.next_id - self.add_query_history.len() as u64;
+        if id < first_id {
+            return;
+        }
+        if let Some(query) = self.add_query_history[(id - first_id) as usize].take() {
+            self.live_history_num -= 1;
+            //the oldest done which has left the add history makes room, one still in it has to stay so an undo can put it back.
+            //there can't be more dones in the add history than it has room for, so one of them always has
+            if self.done_history.len() == self.add_query_history_num {
+                if let Some(position) = self.done_history.iter().position(|(done_id, _)| *done_id < first_id) {
+                    self.done_history.remove(position);
+                }
+            }
+            self.done_history.push_back((id, query));
+        }
+    }
+
+    //undoes retire for a task which has just been reopened, it can no longer be the target of completed searches.
+    //one done so long ago that it has left the add history isn't looked at again, like any other task that old
+    fn reopen(&mut self, id: u64) {
+        self.active_ids.insert(id);
+        let position = match self.done_history.iter().position(|(done_id, _)| *done_id == id) {
+            Some(position) => position,
+            None => return,
+        };
+        let (_, query) = self.done_history.remove(position).unwrap();
+        let first_id = self.next_id - self.add_query_history.len() as u64;
+        if id >= first_id {
+            self.add_query_history[(id - first_id) as usize] = Some(query);
+            self.live_history_num += 1;
+        }
+    }
+
+    //adds tags to any active task
+    fn tag_query<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Query {
+        let id = self.active_ids.pick(rng).unwrap();
+        let mut tags = generate_tags(rng, &self.pool, &self.lengths);
+        if tags.is_empty() {
+            tags.push(self.pool.get_tag(rng).to_owned());
+        }
+        self.vary(rng, &mut tags);
+        if let Some(Query::Add(_, history_tags)) = self.history_entry(id) {
+            history_tags.extend_from_slice(&tags);
+        }
+        Query::Tag(id, tags)
+    }
+
+    //rewrites some of the words or tags in variant forms
+    fn vary<R: Rng + ?Sized>(&self, rng: &mut R, strings: &mut [String]) {
+        if self.variants == 0.0 {
+            return;
+        }
+        for s in strings {
+            if rng.gen_bool(self.variants) {
+                *s = variant(rng, s);
+            }
+        }
+    }
+
+    //puts words which need escaping or trip up tokenisers into a description, from one to three of them anywhere in it
+    //the task keeps all its original words, so searches built from them still find it
+    fn escape<R: Rng + ?Sized>(&self, rng: &mut R, words: &mut Vec<String>) {
+        if self.escapes == 0.0 || !rng.gen_bool(self.escapes) {
+            return;
+        }
+        for _ in 0..rng.gen_range(1..=3) {
+            let word = match rng.gen_range(0..4) {
+                0 => String::new(),
+                1 => format!("\"{}\"", self.pool.get_word(rng)),
+                2 => format!("#{}", self.pool.get_word(rng)),
+                //starting with a character no search term can start with keeps these out of searches however they're cut up
+                _ => {
+                    let first = *['"', '#', '\t'].choose(rng).unwrap();
+                    std::iter::once(first).chain((1..rng.gen_range(1..=3)).map(|_| *['"', '\\', '#', '\t'].choose(rng).unwrap())).collect()
+                },
+            };
+            words.insert(rng.gen_range(0..=words.len()), word);
+        }
+    }
+
+    fn vary_terms<R: Rng + ?Sized>(&self, rng: &mut R, terms: &mut [WordOrTag]) {
+        if self.variants == 0.0 {
+            return;
+        }
+        for term in terms {
+            let (WordOrTag::Word(s) | WordOrTag::Tag(s)) = term;
+            if rng.gen_bool(self.variants) {
+                *s = variant(rng, s);
+            }
+        }
+    }
+
+    fn vary_expr<R: Rng + ?Sized>(&self, rng: &mut R, expr: &mut SearchExpr) {
+        match expr {
+            SearchExpr::Term(term) => self.vary_terms(rng, std::slice::from_mut(term)),
+            SearchExpr::Not(expr) => self.vary_expr(rng, expr),
+            SearchExpr::And(exprs) | SearchExpr::Or(exprs) => {
+                for expr in exprs {
+                    self.vary_expr(rng, expr);
+                }
+            },
+        }
+    }
+
+    //the add of a task which is in the history and still active, kept up to date as the task is changed
+    fn history_entry(&mut self, id: u64) -> Option<&mut Query> {
+        let first_id = self.next_id - self.add_query_history.len() as u64;
+        if id < first_id {
+            return None;
+        }
+        self.add_query_history[(id - first_id) as usize].as_mut()
+    }
+
+    //an add from the history whose task is still active and has something to search for, None if there isn't one
+    fn live_add_query<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<&Query> {
+        let index = self.live_history_index(rng, has_terms)?;
+        self.add_query_history[index].as_ref()
+    }
+
+    //an add from the history whose task has been marked done and has something to search for, None if there isn't one
+    fn completed_add_query<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<&Query> {
+        if self.done_history.is_empty() {
+            return None;
+        }
+        //as with live adds, nearly all have terms so a few random picks nearly always find one
+        for _ in 0..8 {
+            let (_, add_query) = &self.done_history[rng.gen_range(0..self.done_history.len())];
+            if has_terms(add_query) {
+                return Some(add_query);
+            }
+        }
+        let wanted: Vec<&Query> = self.done_history.iter().map(|(_, add_query)| add_query).filter(|add_query| has_terms(add_query)).collect();
+        wanted.choose(rng).copied()
+    }
+
+    //whether there's a done add a completed search can be built from
+    fn can_complete(&self) -> bool {
+        self.done_history.iter().any(|(_, add_query)| has_terms(add_query))
+    }
+
+    //index in the history of an add whose task is still active and which is_wanted, all equally likely
+    fn live_history_index<R: Rng + ?Sized, F: Fn(&Query) -> bool>(&self, rng: &mut R, is_wanted: F) -> Option<usize> {
+        if self.live_history_num == 0 {
+            return None;
+        }
+        //most of the history is usually still active, so a few random picks nearly always find one
+        for _ in 0..8 {
+            let index = rng.gen_range(0..self.add_query_history.len());
+            if self.add_query_history[index].as_ref().is_some_and(&is_wanted) {
+                return Some(index);
+            }
+        }
+        let wanted: Vec<usize> = (0..self.add_query_history.len())
+            .filter(|index| self.add_query_history[*index].as_ref().is_some_and(&is_wanted))
+            .collect();
+        wanted.choose(rng).copied()
+    }
+}
+
+//picks one of the choices in proportion to its weight, or None if every weight is zero
+fn choose<T: Copy, R: Rng + ?Sized>(rng: &mut R, choices: &[(T, u32)]) -> Option<T> {
+    let total: u32 = choices.iter().map(|(_, weight)| weight).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut roll = rng.gen_range(0..total);
+    for (choice, weight) in choices.iter() {
+        if roll < *weight {
+            return Some(*choice);
         }
+        roll -= weight;
     }
+    None //unreachable
+}
+
+pub struct Queries<'a, R: Rng + ?Sized> {
+    generator: &'a mut Generator,
+    rng: &'a mut R,
 }
 
-fn generate_add(rng: &mut ThreadRng, pool: &Pool) -> Query {
+impl<'a, R: Rng + ?Sized> Iterator for Queries<'a, R> {
+    type Item = Query;
+
+    fn next(&mut self) -> Option<Query> {
+        Some(self.generator.get_query(self.rng))
+    }
+}
+
+fn generate_words<R: Rng + ?Sized>(rng: &mut R, pool: &Pool, lengths: &Lengths) -> Vec<String> {
     let mut words = vec![pool.get_word(rng).to_owned()];
-    let mut tags = vec![];
-    let extra_words = match rng.gen_range::<u8, _>(1..=8) {
-        1..=4 => 0,                 //50%
-        5..=6 => 1,                 //25%
-        7 => 2,                     //12.5%
-        8 => 3,                     //12.5%
-        _ => Default::default(),    //0%
-    };
-    for _ in 0..extra_words {
+    for _ in 0..lengths.add_words.sample(rng) {
         words.push(pool.get_word(rng).to_owned());
     }
-    let extra_tags = match rng.gen_range::<u8, _>(1..=8) {
-        1..=4 => 0,                 //50%
-        5..=6 => 1,                 //25%
-        7 => 2,                     //12.5%
-        8 => 3,                     //12.5%
-        _ => Default::default(),    //0%
-    };
-    for _ in 0..extra_tags {
+    words
+}
+
+fn generate_tags<R: Rng + ?Sized>(rng: &mut R, pool: &Pool, lengths: &Lengths) -> Vec<String> {
+    let mut tags = vec![];
+    for _ in 0..lengths.add_tags.sample(rng) {
         tags.push(pool.get_tag(rng).to_owned());
     }
-    Query::Add(words, tags)
+    tags
 }
 
 fn generate_done(n: u64) -> Query {
     Query::Done(n)
 }
 
-fn generate_search(rng: &mut ThreadRng, add_query: &Query) -> Query {
-    match rng.gen_range::<u8, _>(1..=4) {
-        1..=3 => guaranteed_search(rng, add_query),     //75%
-        4 => random_search(rng),                        //25%
-        _ => Default::default(),                        //0%
+//whether a word of a description can be used as a search term, which rules out the ones put in by escapes
+//backslashes are fine, segments can contain them and search terms are written without escapes
+fn is_searchable(word: &str) -> bool {
+    !word.is_empty() && !word.starts_with(['-', '#']) && !word.contains(|c: char| c.is_whitespace() || ['"', '(', ')', '|'].contains(&c))
+}
+
+//whether an add has a word or tag a search can be built from
+fn has_terms(add_query: &Query) -> bool {
+    match add_query {
+        Query::Add(words, tags) => !tags.is_empty() || words.iter().any(|word| is_searchable(word)),
+        _ => false,
     }
 }
 
-//search not technically guaranteed since it's possible to have recent "add" query already done when search for it happens
-//this becomes less likely later on when recent additions make up smaller number of available items to mark as "done"
-fn guaranteed_search(rng: &mut ThreadRng, add_query: &Query) -> Query { 
+//builds a search which matches the given add's task, so it finds that task as long as it's still active
+fn guaranteed_search<R: Rng + ?Sized>(rng: &mut R, add_query: &Query, lengths: &Lengths, vocabulary: &Vocabulary, matching: Matching) -> Query {
     if let Query::Add(words, tags) = add_query {
-        let matched_words = 1 + match rng.gen_range::<u8, _>(1..=8) {
-            1..=4 => 0,                 //50%
-            5..=6 => 1,                 //25%
-            7 => 2,                     //12.5%
-            8 => 3,                     //12.5%
-            _ => Default::default(),    //0%
-        };
-        let matched_words = cmp::min(matched_words, words.len());
-        let matched_tags = match rng.gen_range::<u8, _>(1..=8) {
-            1..=4 => 0,                 //50%
-            5..=6 => 1,                 //25%
-            7 => 2,                     //12.5%
-            8 => 3,                     //12.5%
-            _ => Default::default(),    //0%
-        };
-        let matched_tags = cmp::min(matched_tags, tags.len());
+        let words: Vec<&String> = words.iter().filter(|word| is_searchable(word)).collect();
+        let matched_words = cmp::min(1 + lengths.search_words.sample(rng), words.len());
+        let matched_tags = cmp::min(lengths.search_tags.sample(rng), tags.len());
         let mut search_params = vec![];
-        for i in 0..matched_words {
-            if rng.gen_range(0..4) == 0 {
-                search_params.push(WordOrTag::Word(words[i].to_owned()));
-            }
-            else {
-                search_params.push(WordOrTag::Word(random_subsequence(rng, &words[i])));
-            }
+        for word in words.into_iter().take(matched_words) {
+            search_params.push(WordOrTag::Word(matching_term(rng, word, vocabulary, matching)));
         }
-        for i in 0..matched_tags {
-            if rng.gen_range(0..4) == 0 {
-                search_params.push(WordOrTag::Tag(tags[i].to_owned()));
-            }
-            else {
-                search_params.push(WordOrTag::Tag(random_subsequence(rng, &tags[i])));
-            }
+        for tag in tags.iter().take(matched_tags) {
+            search_params.push(WordOrTag::Tag(matching_term(rng, tag, vocabulary.tags(), matching)));
         }
         search_params.shuffle(rng);
         return Query::Search(search_params)
@@ -139,108 +991,769 @@ fn guaranteed_search(rng: &mut ThreadRng, add_query: &Query) -> Query {
     Default::default() //unreachable
 }
 
-fn random_subsequence(rng: &mut ThreadRng, s: &str) -> String {
-    let mut stri = s.to_owned();
-    for _ in 0..(rng.gen_range(0..stri.len())) {
-        stri.remove(rng.gen_range(0..stri.len()));
+//an expression which the target add matches, made of one or more clauses which all have to match:
+//the first is the target's term or a term from one of the others, then more terms from the target, some also or'd with others' terms,
+//and sometimes a not of another's term, or of a group of them, which the target doesn't match
+//the target's words and tags can be variants of others', so checking it doesn't match a not needs the folding as well as the matching
+fn boolean_search<R: Rng + ?Sized>(rng: &mut R, target: &Query, others: &[&Query], lengths: &Lengths, vocabulary: &Vocabulary, matching: Matching, folding: Folding) -> SearchExpr {
+    let other_term = |rng: &mut R| match others.choose(rng) {
+        Some(other) => derived_term(rng, other, vocabulary, matching),
+        None => WordOrTag::Word(random_word(rng, vocabulary)),
+    };
+    let mut first = vec![SearchExpr::Term(derived_term(rng, target, vocabulary, matching)), SearchExpr::Term(other_term(rng))];
+    first.shuffle(rng);
+    let mut clauses = vec![SearchExpr::Or(first)];
+    for _ in 0..lengths.search_words.sample(rng) {
+        let term = SearchExpr::Term(derived_term(rng, target, vocabulary, matching));
+        if rng.gen_bool(0.5) {
+            clauses.push(term);
+        }
+        else {
+            clauses.push(SearchExpr::Or(vec![term, SearchExpr::Term(other_term(rng))]));
+        }
     }
-    stri
+    let (words, tags) = match target {
+        Query::Add(words, tags) => (words, tags),
+        _ => unreachable!(),
+    };
+    if rng.gen_bool(0.5) {
+        let fold_all = |strings: &[String]| -> Vec<String> { strings.iter().map(|s| folding.fold(s).into_owned()).collect() };
+        let (words, tags) = (fold_all(words), fold_all(tags));
+        let excluded: Vec<SearchExpr> = (0..rng.gen_range(1..=2))
+            .map(|_| SearchExpr::Term(other_term(rng)))
+            .filter(|term| !crate::oracle::expr_matches(&crate::oracle::fold_expr(term, folding), &words, &tags, matching))
+            .collect();
+        match excluded.len() {
+            0 => (),
+            1 => clauses.push(SearchExpr::Not(Box::new(excluded.into_iter().next().unwrap()))),
+            _ => clauses.push(SearchExpr::Not(Box::new(SearchExpr::Or(excluded)))),
+        }
+    }
+    clauses.shuffle(rng);
+    SearchExpr::And(clauses)
+}
+
+//one of the add's words or tags, or a term derived from it, which the add matches
+fn derived_term<R: Rng + ?Sized>(rng: &mut R, add_query: &Query, vocabulary: &Vocabulary, matching: Matching) -> WordOrTag {
+    let (words, tags) = match add_query {
+        Query::Add(words, tags) => (words, tags),
+        _ => unreachable!(),
+    };
+    let words: Vec<&String> = words.iter().filter(|word| is_searchable(word)).collect();
+    let index = rng.gen_range(0..words.len() + tags.len());
+    let (is_tag, s, vocabulary) = match index < words.len() {
+        true => (false, words[index], vocabulary),
+        false => (true, &tags[index - words.len()], vocabulary.tags()),
+    };
+    let term = matching_term(rng, s, vocabulary, matching);
+    match is_tag {
+        true => WordOrTag::Tag(term),
+        false => WordOrTag::Word(term),
+    }
+}
+
+//every word and tag of the add, in a random order, with the budget's worth of typos in each for typo matching
+fn exact_search<R: Rng + ?Sized>(rng: &mut R, add_query: &Query, vocabulary: &Vocabulary, matching: Matching) -> Vec<WordOrTag> {
+    let mut exact = |s: &str, vocabulary: &Vocabulary| match matching {
+        Matching::Typo(budget) => random_typo(rng, s, budget, vocabulary.alphabet()),
+        _ => s.to_owned(),
+    };
+    let mut terms = vec![];
+    if let Query::Add(words, tags) = add_query {
+        for word in words.iter().filter(|word| is_searchable(word)) {
+            terms.push(WordOrTag::Word(exact(word, vocabulary)));
+        }
+        for tag in tags {
+            terms.push(WordOrTag::Tag(exact(tag, vocabulary.tags())));
+        }
+    }
+    terms.shuffle(rng);
+    terms
 }
 
-fn random_search(rng: &mut ThreadRng) -> Query {
-    let generated_words = 1 + match rng.gen_range::<u8, _>(1..=8) {
-        1..=4 => 0,                 //50%
-        5..=6 => 1,                 //25%
-        7 => 2,                     //12.5%
-        8 => 3,                     //12.5%
-        _ => Default::default(),    //0%
+//removes a character from one of the terms in a way that keeps everything it matched, or the whole term if that can't be done,
+//so from anywhere for subsequences, the end for prefixes and either end for substrings, while exact and typo terms can only be dropped
+//false once there's a single term left which can't be shortened, since an empty search would match everything
+fn broaden<R: Rng + ?Sized>(rng: &mut R, terms: &mut Vec<WordOrTag>, matching: Matching) -> bool {
+    let can_shorten = |term: &WordOrTag| match term {
+        WordOrTag::Word(term) | WordOrTag::Tag(term) => {
+            term.graphemes(true).nth(1).is_some() && !matches!(matching, Matching::Exact | Matching::Typo(_))
+        },
+    };
+    if terms.is_empty() || terms.len() == 1 && !can_shorten(&terms[0]) {
+        return false;
+    }
+    let index = rng.gen_range(0..terms.len());
+    if !can_shorten(&terms[index]) {
+        terms.remove(index);
+        return true;
+    }
+    let term = match &mut terms[index] {
+        WordOrTag::Word(term) | WordOrTag::Tag(term) => term,
     };
-    let generated_tags = match rng.gen_range::<u8, _>(1..=8) {
-        1..=4 => 0,                 //50%
-        5..=6 => 1,                 //25%
-        7 => 2,                     //12.5%
-        8 => 3,                     //12.5%
-        _ => Default::default(),    //0%
+    let mut graphemes = graphemes(term);
+    let mut i = match matching {
+        Matching::Prefix => graphemes.len() - 1,
+        Matching::Substring => if rng.gen() { 0 } else { graphemes.len() - 1 },
+        _ => rng.gen_range(0..graphemes.len()),
     };
+    if i == 0 && graphemes[1] == "-" {
+        i = match matching {
+            Matching::Substring => graphemes.len() - 1,
+            _ => 1,
+        };
+    }
+    graphemes.remove(i);
+    *term = graphemes.concat();
+    true
+}
+
+//inserts a random alphabet entry somewhere in one of the search's terms
+fn near_miss<R: Rng + ?Sized>(rng: &mut R, search: Query, vocabulary: &Vocabulary) -> Query {
+    if let Query::Search(mut terms) = search {
+        let index = rng.gen_range(0..terms.len());
+        let (term, alphabet) = match &mut terms[index] {
+            WordOrTag::Word(term) => (term, vocabulary.alphabet()),
+            WordOrTag::Tag(term) => (term, vocabulary.tags().alphabet()),
+        };
+        let c = &alphabet[rng.gen_range(0..alphabet.len())];
+        let mut graphemes = graphemes(term);
+        let first = if c == "-" { 1 } else { 0 };
+        graphemes.insert(rng.gen_range(first..=graphemes.len()), c);
+        *term = graphemes.concat();
+        return Query::Search(terms)
+    }
+    search
+}
+
+//a term which matches the word or tag s, a quarter of the time s itself and otherwise derived from it to suit the matching
+//typo terms are always exactly the budget away from s, so the distance of every one is the one recorded in the metadata
+//the vocabulary is the one s was built from, which typos draw from
+fn matching_term<R: Rng + ?Sized>(rng: &mut R, s: &str, vocabulary: &Vocabulary, matching: Matching) -> String {
+    if let Matching::Typo(budget) = matching {
+        return random_typo(rng, s, budget, vocabulary.alphabet());
+    }
+    if rng.gen_range(0..4) == 0 {
+        return s.to_owned();
+    }
+    match matching {
+        Matching::Exact | Matching::Typo(_) => s.to_owned(),
+        Matching::Prefix => random_prefix(rng, s),
+        Matching::Substring => random_substring(rng, s),
+        Matching::Subsequence => random_subsequence(rng, s),
+    }
+}
+
+//the same word written differently: in mixed case, title case, full-width characters, nfd or nfc
+//only letters whose uppercase lowercases back to them are changed, so "ß" is left alone rather than becoming "SS"
+fn variant<R: Rng + ?Sized>(rng: &mut R, s: &str) -> String {
+    let upper = |g: &str| {
+        let upper = g.to_uppercase();
+        match upper.to_lowercase() == g {
+            true => upper,
+            false => g.to_owned(),
+        }
+    };
+    match rng.gen_range(0..5) {
+        0 => graphemes(s).into_iter().map(|g| if rng.gen() { upper(g) } else { g.to_owned() }).collect(),
+        1 => {
+            let graphemes = graphemes(s);
+            (0..graphemes.len())
+                .map(|i| if i == 0 || graphemes[i - 1] == "-" { upper(graphemes[i]) } else { graphemes[i].to_owned() })
+                .collect()
+        },
+        2 => s.chars().map(|c| match c {
+            '!'..='~' => char::from_u32(c as u32 - '!' as u32 + '！' as u32).unwrap(),
+            c => c,
+        }).collect(),
+        3 => s.nfd().collect(),
+        _ => s.nfc().collect(),
+    }
+}
+
+//every string the generator cuts up is handled a grapheme cluster at a time, so accents stay on their letters and emoji stay whole
+fn graphemes(s: &str) -> Vec<&str> {
+    s.graphemes(true).collect()
+}
+
+fn random_prefix<R: Rng + ?Sized>(rng: &mut R, s: &str) -> String {
+    let graphemes = graphemes(s);
+    let length = rng.gen_range(1..=graphemes.len());
+    graphemes[..length].concat()
+}
+
+//as for subsequences, a substring can't start with a hyphen, so the start is moved past any
+fn random_substring<R: Rng + ?Sized>(rng: &mut R, s: &str) -> String {
+    let graphemes = graphemes(s);
+    let mut start = rng.gen_range(0..graphemes.len());
+    let end = rng.gen_range(start + 1..=graphemes.len());
+    while start < end && graphemes[start] == "-" {
+        start += 1;
+    }
+    match start < end {
+        true => graphemes[start..end].concat(),
+        false => graphemes[0].to_owned(),
+    }
+}
+
+//s exactly the given number of typos away, each a grapheme deleted, inserted, doubled, changed to another from the alphabet or swapped with the next
+//a typo which would leave a leading hyphen or change nothing is rerolled, doubling always works so that can't go on forever
+//later typos can undo earlier ones or combine into fewer edits, so the result is checked and the typos made again if it's too close,
+//falling back to doubling the last grapheme that many times, which is always exactly that far away
+//the distance is measured as written, folding can bring a typo which only changes case closer
+fn random_typo<R: Rng + ?Sized>(rng: &mut R, s: &str, typos: u32, alphabet: &[String]) -> String {
+    for _ in 0..TYPO_ATTEMPTS {
+        let typo = typos_of(rng, s, typos, alphabet);
+        if !within_distance(&typo, s, typos as usize - 1) {
+            return typo;
+        }
+    }
+    let last = graphemes(s).pop().unwrap_or_default();
+    format!("{}{}", s, last.repeat(typos as usize))
+}
+
+fn typos_of<R: Rng + ?Sized>(rng: &mut R, s: &str, typos: u32, alphabet: &[String]) -> String {
+    let mut graphemes = graphemes(s);
+    for _ in 0..typos {
+        loop {
+            let i = rng.gen_range(0..graphemes.len());
+            let c = alphabet[rng.gen_range(0..alphabet.len())].as_str();
+            let leaves_hyphen = i == 0 && graphemes.get(1) == Some(&"-");
+            match rng.gen_range(0..5) {
+                0 if graphemes.len() > 1 && !leaves_hyphen => {
+                    graphemes.remove(i);
+                },
+                1 if i > 0 || c != "-" => graphemes.insert(i, c),
+                2 => graphemes.insert(i, graphemes[i]),
+                3 if (i > 0 || c != "-") && c != graphemes[i] => graphemes[i] = c,
+                4 if i + 1 < graphemes.len() && graphemes[i] != graphemes[i + 1] && !leaves_hyphen => graphemes.swap(i, i + 1),
+                _ => continue,
+            }
+            break;
+        }
+    }
+    graphemes.concat()
+}
+
+//words and tags never start with a hyphen, but what's left of them might, so any leading hyphens are dropped as well
+fn random_subsequence<R: Rng + ?Sized>(rng: &mut R, s: &str) -> String {
+    let mut graphemes = graphemes(s);
+    for _ in 0..(rng.gen_range(0..graphemes.len())) {
+        graphemes.remove(rng.gen_range(0..graphemes.len()));
+    }
+    while graphemes.first() == Some(&"-") {
+        graphemes.remove(0);
+    }
+    match graphemes.is_empty() {
+        true => s.graphemes(true).take(1).collect(),
+        false => graphemes.concat(),
+    }
+}
+
+fn random_search<R: Rng + ?Sized>(rng: &mut R, lengths: &Lengths, vocabulary: &Vocabulary) -> Query {
+    let generated_words = 1 + lengths.search_words.sample(rng);
+    let generated_tags = lengths.search_tags.sample(rng);
     let mut search_params = vec![];
     for _ in 0..generated_words {
-        search_params.push(WordOrTag::Word(random_word(rng)));
+        search_params.push(WordOrTag::Word(random_word(rng, vocabulary)));
     }
     for _ in 0..generated_tags {
-        search_params.push(WordOrTag::Tag(random_word(rng)));
+        search_params.push(WordOrTag::Tag(random_word(rng, vocabulary.tags())));
     }
     search_params.shuffle(rng);
-    return Query::Search(search_params)
+    Query::Search(search_params)
 }
 
-fn random_word(rng: &mut ThreadRng) -> String {
+fn random_word<R: Rng + ?Sized>(rng: &mut R, vocabulary: &Vocabulary) -> String {
     let max_length = 7;
+    let alphabet = vocabulary.alphabet();
+    let length = rng.gen_range::<u8, _>(1..=max_length) as usize;
+    random_term(rng, alphabet, length)
+}
+
+//a term of random alphabet entries which doesn't start with a hyphen, so it can't be read as a not
+fn random_term<R: Rng + ?Sized>(rng: &mut R, alphabet: &[String], length: usize) -> String {
     let mut stri = String::new();
-    for _ in 0..rng.gen_range::<u8, _>(1..=max_length) {
-        stri.push(CHARS[rng.gen_range(0..CHARS.len())]);
+    for _ in 0..length {
+        let mut c = &alphabet[rng.gen_range(0..alphabet.len())];
+        while stri.is_empty() && c == "-" {
+            c = &alphabet[rng.gen_range(0..alphabet.len())];
+        }
+        stri.push_str(c);
     }
     stri
 }
 
-mod pool {
+pub mod pool {
     use super::*;
 
     pub struct Pool {
-        word_num: usize,
-        tag_num: usize,
         word_pool: Vec<String>,
         tag_pool: Vec<String>,
+        word_picker: Picker,
+        tag_picker: Picker,
     }
 
     impl Pool {
-        pub fn new(rng: &mut ThreadRng, word_num: usize, tag_num: usize) -> Pool {
+        pub fn new<R: Rng + ?Sized>(rng: &mut R, word_num: usize, tag_num: usize, lengths: &Lengths, vocabulary: &Vocabulary) -> Pool {
             Pool {
-                word_num,
-                tag_num,
-                word_pool: generate_pool(rng, word_num),
-                tag_pool: generate_pool(rng, tag_num),
+                word_pool: generate_pool(rng, word_num, lengths, vocabulary),
+                tag_pool: generate_pool(rng, tag_num, lengths, vocabulary.tags()),
+                word_picker: Picker::new(&Popularity::Uniform, word_num),
+                tag_picker: Picker::new(&Popularity::Uniform, tag_num),
             }
         }
 
-        pub fn get_word(&self, rng: &mut ThreadRng) -> &str {
-            &self.word_pool[rng.gen_range(0..self.word_num)]
+        //words and tags are drawn uniformly unless given another popularity
+        pub fn with_popularity(mut self, words: &Popularity, tags: &Popularity) -> Pool {
+            self.word_picker = Picker::new(words, self.word_pool.len());
+            self.tag_picker = Picker::new(tags, self.tag_pool.len());
+            self
+        }
+
+        pub fn get_word<R: Rng + ?Sized>(&self, rng: &mut R) -> &str {
+            &self.word_pool[self.word_picker.pick(rng)]
         }
 
-        pub fn get_tag(&self, rng: &mut ThreadRng) -> &str {
-            &self.tag_pool[rng.gen_range(0..self.tag_num)]
+        pub fn get_tag<R: Rng + ?Sized>(&self, rng: &mut R) -> &str {
+            &self.tag_pool[self.tag_picker.pick(rng)]
+        }
+
+        //length in grapheme clusters of the longest word or tag
+        pub fn longest(&self) -> usize {
+            self.word_pool.iter().chain(&self.tag_pool).map(|s| s.graphemes(true).count()).max().unwrap_or(0)
         }
     }
 
-    fn generate_pool(rng: &mut ThreadRng, num: usize) -> Vec<String> {
+    fn generate_pool<R: Rng + ?Sized>(rng: &mut R, num: usize, lengths: &Lengths, vocabulary: &Vocabulary) -> Vec<String> {
         let mut pool = vec![];
         for _ in 0..num {
-            pool.push(generate_drop(rng))
+            pool.push(generate_drop(rng, lengths, vocabulary.segments()))
         }
         pool
     }
     
-    fn generate_drop(rng: &mut ThreadRng) -> String {
-        let mut drop = SEGMENTS[rng.gen_range(0..=999)].to_owned();
-        let extra_segments = match rng.gen_range::<u8, _>(1..=8) {
-            1..=4 => 0,                 //50%
-            5..=6 => 1,                 //25%
-            7 => 2,                     //12.5%
-            8 => 3,                     //12.5%
-            _ => Default::default(),    //0%
-        };
-        for _ in 0..extra_segments {
+    fn generate_drop<R: Rng + ?Sized>(rng: &mut R, lengths: &Lengths, segments: &[String]) -> String {
+        let mut drop = segments[rng.gen_range(0..segments.len())].to_owned();
+        for _ in 0..lengths.pool_segments.sample(rng) {
             drop.push('-');
-            let extra_hyphens = match rng.gen_range::<u8, _>(1..=20) {
-                1..=18 => 0,                //90%
-                19 => 1,                    //5%
-                20 => 2,                    //5%
-                _ => Default::default(),    //0%
-            };
-            for _ in 0..extra_hyphens {
+            for _ in 0..lengths.pool_hyphens.sample(rng) {
                 drop.push('-');
             }
-            drop.push_str(SEGMENTS[rng.gen_range(0..=999)]);
+            drop.push_str(&segments[rng.gen_range(0..segments.len())]);
         }
         drop
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_pcg::Pcg64;
+
+    #[test]
+    fn query_weights_test() {
+        assert_eq!("done=3,search=0".parse(), Ok(QueryWeights { done: 3, search: 0, ..Default::default() }));
+        assert_eq!("undo=1".parse::<QueryWeights>().map(|weights| weights.undo), Ok(1));
+        assert!("remove=1".parse::<QueryWeights>().is_err());
+        assert!("add".parse::<QueryWeights>().is_err());
+
+        //searches are never generated when their weight is zero and dones only once there is something to mark done
+        let rng = &mut Pcg64::seed_from_u64(0);
+        let mut generator = Generator::builder().weights(QueryWeights { add: 0, done: 1, search: 0, ..Default::default() }).build(rng).unwrap();
+        assert!(matches!(generator.get_query(rng), Query::Add(..)));
+        assert_eq!(generator.get_query(rng), Query::Done(0));
+        assert!(matches!(generator.get_query(rng), Query::Add(..)));
+    }
+
+    #[test]
+    fn query_kinds_test() {
+        //every kind is only issued against tasks it's valid for, and hits still find what they target as tasks change
+        let rng = &mut Pcg64::seed_from_u64(0);
+        let weights = QueryWeights { add: 4, done: 2, search: 2, edit: 1, tag: 1, untag: 1, undo: 1, list: 1 };
+        let mut generator = Generator::builder()
+            .weights(weights)
+            .search_weights(SearchWeights { hit: 1, random: 0, ..Default::default() })
+            .history(20)
+            .build(rng)
+            .unwrap();
+        let mut tasks = crate::oracle::TaskList::new();
+        let mut seen = std::collections::BTreeSet::new();
+        for query in generator.queries(rng).take(5000) {
+            let answer = tasks.apply(&query).to_string();
+            assert!(!answer.starts_with("error"), "{} gave {}", query, answer);
+            if let Query::Search(_) = query {
+                assert!(!answer.starts_with("0"), "{} found nothing", query);
+            }
+            seen.insert(query.command());
+        }
+        assert_eq!(seen.len(), 8);
+    }
+
+    #[test]
+    fn search_weights_test() {
+        assert_eq!("completed=1".parse(), Ok(SearchWeights { completed: 1, ..Default::default() }));
+        assert!("near=1".parse::<SearchWeights>().is_err());
+        let rng = &mut Pcg64::seed_from_u64(0);
+        assert!(Generator::builder().search_weights(SearchWeights { hit: 0, random: 0, ..Default::default() }).build(rng).is_err());
+
+        //hits always find something, even when most recent adds are soon marked done
+        let mut generator = Generator::builder()
+            .weights(QueryWeights { add: 2, done: 2, search: 1, ..Default::default() })
+            .search_weights(SearchWeights { hit: 1, random: 0, ..Default::default() })
+            .done_policy(DonePolicy::Newest)
+            .history(10)
+            .build(rng)
+            .unwrap();
+        let mut tasks = crate::oracle::TaskList::new();
+        for query in generator.queries(rng).take(5000) {
+            let answer = tasks.apply(&query);
+            if let Query::Search(_) = query {
+                assert!(!answer.to_string().starts_with("0"), "{} found nothing", query);
+            }
+        }
+
+        //boolean searches always find what they're built from too, and read back as they were written
+        let mut generator = Generator::builder()
+            .search_weights(SearchWeights { hit: 0, random: 0, boolean: 1, ..Default::default() })
+            .build(rng)
+            .unwrap();
+        let mut tasks = crate::oracle::TaskList::new();
+        for query in generator.queries(rng).take(2000) {
+            let answer = tasks.apply(&query);
+            if query.command() == "search" {
+                assert!(matches!(query, Query::BooleanSearch(_)));
+                assert!(!answer.to_string().starts_with("0"), "{} found nothing", query);
+                assert_eq!(query.to_string().parse::<Query>().unwrap(), query);
+            }
+        }
+
+        //and misses never find anything
+        let mut generator = Generator::builder()
+            .words(10)
+            .tags(5)
+            .search_weights(SearchWeights { hit: 0, random: 0, miss: 1, ..Default::default() })
+            .build(rng)
+            .unwrap();
+        let mut tasks = crate::oracle::TaskList::new();
+        for query in generator.queries(rng).take(5000) {
+            let answer = tasks.apply(&query);
+            if let Query::Search(_) = query {
+                assert_eq!(answer.to_string(), "0", "{} found something", query);
+            }
+        }
+    }
+
+    #[test]
+    fn selectivity_test() {
+        let rng = &mut Pcg64::seed_from_u64(0);
+        for band in ["1", "2-10", "10%+"].iter() {
+            let selectivity: Selectivity = band.parse().unwrap();
+            let mut generator = Generator::builder().words(1000).tags(100).selectivity(selectivity).build(rng).unwrap();
+            let mut tasks = crate::oracle::TaskList::new();
+            let (mut searches, mut fitting) = (0, 0);
+            for query in generator.queries(rng).take(1500) {
+                let (min, max) = selectivity.range(tasks.search(&[]).len());
+                if let crate::oracle::Answer::Found(ids) = tasks.apply(&query) {
+                    searches += 1;
+                    if min <= ids.len() && ids.len() <= max {
+                        fitting += 1;
+                    }
+                }
+            }
+            assert!(fitting * 100 > searches * 95, "only {} of {} searches found {}", fitting, searches, band);
+        }
+    }
+
+    #[test]
+    fn matching_test() {
+        //hits, selective searches and booleans find what they came from and misses find nothing, however terms match
+        let rng = &mut Pcg64::seed_from_u64(0);
+        for matching in ["exact", "prefix", "substring", "subsequence", "typo", "typo:3"].iter() {
+            let matching: Matching = matching.parse().unwrap();
+            for (search_weights, hits) in [("hit=2,boolean=1,random=0", true), ("miss=1,hit=0,random=0", false)].iter() {
+                let mut generator = Generator::builder()
+                    .words(200)
+                    .tags(20)
+                    .search_weights(search_weights.parse().unwrap())
+                    .selectivity("1+".parse().unwrap())
+                    .matching(matching)
+                    .build(rng)
+                    .unwrap();
+                let mut tasks = crate::oracle::TaskList::with_matching(matching);
+                for query in generator.queries(rng).take(500) {
+                    let answer = tasks.apply(&query).to_string();
+                    if query.command() == "search" {
+                        assert_eq!(answer != "0", *hits, "{} matching: {} gave {}", matching, query, answer);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn script_test() {
+        //non-ascii words and tags are cut up whole, so hits still hit, and every query reads back as it was written
+        let rng = &mut Pcg64::seed_from_u64(0);
+        for (script, matching) in [("latin", "subsequence"), ("cyrillic", "prefix"), ("cjk", "substring"), ("emoji", "typo:2")].iter() {
+            let vocabulary = Vocabulary::builtin(script.parse().unwrap()).with_tag_vocabulary(Vocabulary::builtin(Script::Emoji));
+            let matching: Matching = matching.parse().unwrap();
+            let mut generator = Generator::builder()
+                .search_weights("hit=3,boolean=1,random=0".parse().unwrap())
+                .vocabulary(vocabulary)
+                .matching(matching)
+                .build(rng)
+                .unwrap();
+            let mut tasks = crate::oracle::TaskList::with_matching(matching);
+            for query in generator.queries(rng).take(1000) {
+                let answer = tasks.apply(&query).to_string();
+                if query.command() == "search" {
+                    assert_ne!(answer, "0", "{} with {} matching found nothing", query, matching);
+                }
+                assert_eq!(query.to_string().parse::<Query>().unwrap(), query);
+            }
+        }
+    }
+
+    #[test]
+    fn typo_test() {
+        //typos are always exactly that many edits away, and none leave a leading hyphen
+        let rng = &mut Pcg64::seed_from_u64(0);
+        let alphabet = Vocabulary::default().alphabet().to_vec();
+        for word in ["a", "ab", "a-b", "milk", "oat--milk"].iter() {
+            for typos in 1..=3 {
+                for _ in 0..100 {
+                    let typo = random_typo(rng, word, typos, &alphabet);
+                    assert!(!typo.starts_with('-'), "{} from {}", typo, word);
+                    assert!(within_distance(&typo, word, typos as usize), "{} from {}", typo, word);
+                    assert!(!within_distance(&typo, word, typos as usize - 1), "{} from {}", typo, word);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn variants_test() {
+        //every variant folds back to the word it was made from
+        let rng = &mut Pcg64::seed_from_u64(0);
+        for word in ["milk", "oat-milk", "crème-brûlée", "straße", "молоко"].iter() {
+            for _ in 0..20 {
+                let variant = variant(rng, word);
+                assert_eq!(Folding::Folded.fold(&variant), Folding::Folded.fold(word), "{} from {}", variant, word);
+            }
+        }
+        //hits still hit when tasks and, if folded, search terms are written as variants
+        for (script, folding) in [("ascii", Folding::Folded), ("latin", Folding::Folded), ("latin", Folding::Exact)].iter() {
+            let mut generator = Generator::builder()
+                .search_weights("hit=2,boolean=1,random=0".parse().unwrap())
+                .vocabulary(Vocabulary::builtin(script.parse().unwrap()))
+                .folding(*folding)
+                .variants(0.5)
+                .build(rng)
+                .unwrap();
+            let mut tasks = crate::oracle::TaskList::with_matching(Matching::default()).with_folding(*folding);
+            let mut varied = false;
+            for query in generator.queries(rng).take(1000) {
+                let answer = tasks.apply(&query).to_string();
+                if query.command() == "search" {
+                    assert_ne!(answer, "0", "{} with {} folding found nothing", query, folding);
+                }
+                varied |= query.to_string().chars().any(|c| c.is_uppercase());
+                assert_eq!(query.to_string().parse::<Query>().unwrap(), query);
+            }
+            assert!(varied);
+        }
+        //untag removes every variant of a tag, so a hit never uses one the task has lost
+        let lengths = Lengths { add_tags: "0,0,0,1".parse().unwrap(), ..Default::default() };
+        let mut generator = Generator::builder()
+            .tags(2)
+            .lengths(lengths)
+            .weights("add=2,search=4,untag=3,tag=2".parse().unwrap())
+            .search_weights("hit=1,random=0".parse().unwrap())
+            .matching(Matching::Exact)
+            .folding(Folding::Folded)
+            .variants(0.5)
+            .build(rng)
+            .unwrap();
+        let mut tasks = crate::oracle::TaskList::with_matching(Matching::Exact).with_folding(Folding::Folded);
+        for query in generator.queries(rng).take(5000) {
+            let answer = tasks.apply(&query).to_string();
+            if query.command() == "search" {
+                assert_ne!(answer, "0", "{} found nothing", query);
+            }
+        }
+        assert!(Generator::builder().variants(1.5).build(rng).is_err());
+    }
+
+    #[test]
+    fn completed_test() {
+        //completed searches come from a done task however many of them have nothing to search for, and are only left out without one
+        let rng = &mut Pcg64::seed_from_u64(0);
+        let mut generator = Generator::builder()
+            .weights("add=3,done=2".parse().unwrap())
+            .search_weights("hit=0,random=0,completed=1".parse().unwrap())
+            .matching(Matching::Exact)
+            .build(rng)
+            .unwrap();
+        let queries: Vec<Query> = generator.queries(rng).take(200).collect();
+        let completed = |generator: &Generator| generator.search_kinds().iter().any(|(kind, weight)| matches!(kind, SearchKind::Completed) && *weight > 0);
+        assert!(completed(&generator));
+        let done: Vec<(u64, Query)> = generator.done_history.drain(..).collect();
+        generator.done_history.extend((0..50).map(|id| (id, Query::Add(vec![], vec![]))));
+        assert!(!completed(&generator));
+        assert_eq!(generator.completed_add_query(rng), None);
+
+        generator.done_history.push_back(done[0].clone());
+        assert!(completed(&generator));
+        for _ in 0..100 {
+            assert_eq!(generator.completed_add_query(rng), Some(&done[0].1));
+            match generator.build_search(rng, SearchKind::Completed) {
+                Query::Search(terms) => assert!(terms.iter().all(|term| match (term, &done[0].1) {
+                    (WordOrTag::Word(word), Query::Add(words, _)) => words.contains(word),
+                    (WordOrTag::Tag(tag), Query::Add(_, tags)) => tags.contains(tag),
+                    _ => false,
+                }), "{:?} isn't from {}", terms, done[0].1),
+                query => panic!("{} isn't a search", query),
+            }
+        }
+        assert!(queries.iter().any(|query| matches!(query, Query::Done(_))));
+    }
+
+    #[test]
+    fn reopen_test() {
+        //a done task still in the add history keeps its add however many dones come after it, so an undo always puts it back
+        let rng = &mut Pcg64::seed_from_u64(0);
+        let mut generator = Generator::builder()
+            .weights("add=2,done=3,undo=2".parse().unwrap())
+            .history(4)
+            .build(rng)
+            .unwrap();
+        let mut reopened = 0;
+        for _ in 0..5000 {
+            let query = generator.next_query(rng);
+            reopened += (query == Query::Undo) as u32;
+            let first_id = generator.next_id - generator.add_query_history.len() as u64;
+            for (index, slot) in generator.add_query_history.iter().enumerate() {
+                let id = first_id + index as u64;
+                let done = generator.done_history.iter().any(|(done_id, _)| *done_id == id);
+                assert_eq!(generator.active_ids.contains(id), slot.is_some(), "task {} after {}", id, query);
+                assert_eq!(slot.is_none(), done, "task {} after {}", id, query);
+            }
+            assert!(generator.done_history.len() <= 4);
+            assert_eq!(generator.live_history_num, generator.add_query_history.iter().flatten().count());
+        }
+        assert!(reopened > 100);
+    }
+
+    #[test]
+    fn escapes_test() {
+        //descriptions needing escapes read back as they were written, and hits still hit without using the words put in
+        let rng = &mut Pcg64::seed_from_u64(0);
+        for matching in [Matching::Prefix, Matching::Typo(1)].iter() {
+            let mut generator = Generator::builder()
+                .weights("add=2,edit=1,search=2".parse().unwrap())
+                .search_weights("hit=2,boolean=1,random=0".parse().unwrap())
+                .matching(*matching)
+                .escapes(1.0)
+                .build(rng)
+                .unwrap();
+            let mut tasks = crate::oracle::TaskList::with_matching(*matching);
+            let mut escaped = false;
+            for query in generator.queries(rng).take(1000) {
+                let line = query.to_string();
+                escaped |= line.contains("\\\"");
+                assert_eq!(line.parse::<Query>().unwrap(), query);
+                let answer = tasks.apply(&query).to_string();
+                if query.command() == "search" {
+                    assert_ne!(answer, "0", "{} found nothing", query);
+                }
+                if let Query::Search(terms) = &query {
+                    assert!(terms.iter().all(|term| matches!(term, WordOrTag::Word(s) | WordOrTag::Tag(s) if is_searchable(s))), "{}", query);
+                }
+            }
+            assert!(escaped);
+        }
+        assert!(Generator::builder().escapes(-0.1).build(rng).is_err());
+
+        //backslashes in segments are escaped in descriptions but still searched for
+        let vocabulary = Vocabulary::new(vec!["a\\b".to_string(), "c\\d".to_string()], vec!["a".to_string(), "\\".to_string()]).unwrap();
+        let mut generator = Generator::builder()
+            .weights("add=2,done=1,search=2".parse().unwrap())
+            .search_weights("hit=2,boolean=1,completed=1,random=0".parse().unwrap())
+            .selectivity("1+".parse().unwrap())
+            .vocabulary(vocabulary)
+            .escapes(0.5)
+            .build(rng)
+            .unwrap();
+        let mut tasks = crate::oracle::TaskList::new();
+        for query in generator.queries(rng).take(1000) {
+            let answer = tasks.apply(&query).to_string();
+            assert!(!answer.starts_with("error: empty"), "{}", query);
+            assert_eq!(query.to_string().parse::<Query>().unwrap(), query);
+        }
+    }
+
+    #[test]
+    fn invalid_test() {
+        //invalid input is answered with an error, reads back as it was written and leaves the tasks alone, so hits still hit
+        let rng = &mut Pcg64::seed_from_u64(0);
+        let mut generator = Generator::builder()
+            .weights("add=2,done=1,search=2,undo=1".parse().unwrap())
+            .search_weights("hit=1,random=0".parse().unwrap())
+            .escapes(0.5)
+            .invalid(0.3)
+            .build(rng)
+            .unwrap();
+        let mut tasks = crate::oracle::TaskList::new();
+        let queries: Vec<Query> = generator.queries(rng).take(2000).collect();
+        let mut errors = 0;
+        for query in &queries {
+            let answer = tasks.apply(query).to_string();
+            match query {
+                Query::Invalid(line, error) => {
+                    assert_eq!(line.parse::<Query>().map_err(|e| e.message).as_ref(), Err(error), "{}", line);
+                    assert_eq!(answer, format!("error: {}", error));
+                },
+                Query::Search(terms) if !terms.is_empty() => assert_ne!(answer, "0", "{} found nothing", query),
+                _ => (),
+            }
+            if answer.starts_with("error: ") {
+                errors += 1;
+            }
+        }
+        assert!(errors > 400, "only {} errors", errors);
+        let file: String = std::iter::once(queries.len().to_string()).chain(queries.iter().map(Query::to_string)).map(|line| line + "\n").collect();
+        assert_eq!(crate::parser::parse_queries_with_invalid(&file).unwrap(), queries);
+        assert!(Generator::builder().invalid(2.0).build(rng).is_err());
+
+        //without malformed lines every query still parses and is answered with an error just as often
+        let mut generator = Generator::builder().invalid(0.5).malformed(false).build(rng).unwrap();
+        let mut tasks = crate::oracle::TaskList::new();
+        let mut errors = 0;
+        for query in generator.queries(rng).take(2000) {
+            assert!(!matches!(query, Query::Invalid(..)), "{}", query);
+            if tasks.apply(&query).to_string().starts_with("error: ") {
+                errors += 1;
+            }
+        }
+        assert!(errors > 800, "only {} errors", errors);
+    }
+
+    #[test]
+    fn generator_builder_test() {
+        let rng = &mut Pcg64::seed_from_u64(0);
+        assert!(Generator::builder().words(0).build(rng).is_err());
+        assert!(Generator::builder().tags(0).build(rng).is_err());
+        assert!(Generator::builder().history(0).build(rng).is_err());
+        assert!(Generator::builder().weights(QueryWeights { add: 0, done: 0, search: 0, ..Default::default() }).build(rng).is_err());
+
+        //the same seed gives the same queries
+        let generate = |seed| {
+            let rng = &mut Pcg64::seed_from_u64(seed);
+            let mut generator = Generator::builder().words(20).tags(5).history(10).build(rng).unwrap();
+            generator.queries(rng).take(100).collect::<Vec<_>>()
+        };
+        assert_eq!(generate(1), generate(1));
+        assert_ne!(generate(1), generate(2));
+    }
+}
@@ -0,0 +1,246 @@
+use crate::types::*;
+use crate::oracle::*;
+use crate::matching::{Folding, Matching};
+
+use std::fmt;
+use std::fs;
+use std::io::prelude::*;
+use std::io;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+//how many queries before a wrong answer are shown with it
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub time: Option<Duration>,
+    pub memory: Option<u64>,    //peak resident memory in bytes, only enforced on linux and sampled every few milliseconds
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Accepted { time: Duration, memory: Option<u64> },
+    WrongAnswer(Mismatch),
+    TimeLimitExceeded { limit: Duration },
+    MemoryLimitExceeded { limit: u64, used: u64 },
+    RuntimeError { status: String },
+}
+
+//the first answer which differs from the reference, along with the queries leading up to it
+#[derive(Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    pub index: usize,                           //of the query, counting from 0
+    pub context: Vec<(usize, String)>,          //the query and a few before it, with their indices
+    pub expected: String,
+    pub found: Option<String>,                  //None if the output ended early
+}
+
+//how the candidate's run ended, before its answers are checked
+enum Run {
+    Exited { status: ExitStatus, time: Duration, peak: Option<u64>, output: String },
+    Stopped(Verdict),   //killed for going over a limit
+}
+
+//runs a candidate with the whole query file on stdin and checks its output, one line per query,
+//against the answers of the reference task list matching and folding search terms the given way, ignoring trailing whitespace on each line
+pub fn judge(candidate: &mut Command, input: &str, queries: &[Query], matching: Matching, folding: Folding, limits: &Limits) -> io::Result<Verdict> {
+    if limits.memory.is_some() && !cfg!(target_os = "linux") {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, "memory limits are only supported on linux"));
+    }
+
+    let mut child = candidate
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let start = Instant::now();
+
+    //feed and drain the candidate on other threads so neither side can block on a full pipe
+    let run = thread::scope(|scope| {
+        let mut stdin = child.stdin.take().unwrap();
+        let writer = scope.spawn(move || {
+            let _ = stdin.write_all(input.as_bytes()); //the candidate may exit without reading everything
+        });
+        let mut stdout = child.stdout.take().unwrap();
+        let reader = scope.spawn(move || {
+            let mut output = vec![];
+            stdout.read_to_end(&mut output).map(|_| String::from_utf8_lossy(&output).into_owned())
+        });
+
+        //the peak is the last sample of the candidate's own high water mark, so one which exits before it's first sampled has none
+        let mut peak = None;
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) => (),
+                Err(e) => {
+                    finish(&mut child);
+                    return Err(e);
+                },
+            }
+            if let Some(used) = peak_memory(child.id()) {
+                peak = Some(used);
+                if let Some(limit) = limits.memory.filter(|limit| used > *limit) {
+                    finish(&mut child);
+                    return Ok(Run::Stopped(Verdict::MemoryLimitExceeded { limit, used }));
+                }
+            }
+            if let Some(limit) = limits.time.filter(|limit| start.elapsed() > *limit) {
+                finish(&mut child);
+                return Ok(Run::Stopped(Verdict::TimeLimitExceeded { limit }));
+            }
+            thread::sleep(Duration::from_millis(5));
+        };
+        let time = start.elapsed();
+        let _ = writer.join();
+        let output = reader.join().unwrap()?;
+        Ok(Run::Exited { status, time, peak, output })
+    })?;
+    let (status, time, peak, output) = match run {
+        Run::Exited { status, time, peak, output } => (status, time, peak, output),
+        Run::Stopped(verdict) => return Ok(verdict),
+    };
+
+    if !status.success() {
+        return Ok(Verdict::RuntimeError { status: status.to_string() });
+    }
+    //the answers are worked out one at a time as they're compared, rather than all kept for the whole run
+    let undo = queries.iter().any(|query| matches!(query, Query::Undo));
+    let mut tasks = TaskList::with_matching(matching).with_folding(folding).with_undo(undo);
+    match compare(queries, queries.iter().map(|query| tasks.apply(query).to_string()), &output) {
+        Some(mismatch) => Ok(Verdict::WrongAnswer(mismatch)),
+        None => Ok(Verdict::Accepted { time, memory: peak }),
+    }
+}
+
+fn finish(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+//the expected answers are one per query, in order
+pub fn compare<I: IntoIterator<Item = String>>(queries: &[Query], expected: I, output: &str) -> Option<Mismatch> {
+    let mut found = output.lines();
+    for (index, expected) in expected.into_iter().enumerate() {
+        let found = found.next();
+        if found.map(str::trim_end) != Some(expected.trim_end()) {
+            return Some(Mismatch {
+                index,
+                context: (index.saturating_sub(CONTEXT)..=index).map(|i| (i, queries[i].to_string())).collect(),
+                expected,
+                found: found.map(str::to_owned),
+            });
+        }
+    }
+    found.find(|line| !line.trim().is_empty()).map(|extra| Mismatch {
+        index: queries.len(),
+        context: (queries.len().saturating_sub(CONTEXT)..queries.len()).map(|i| (i, queries[i].to_string())).collect(),
+        expected: "end of output".to_owned(),
+        found: Some(extra.to_owned()),
+    })
+}
+
+//high water mark of the process's own resident memory so far, in bytes
+//it starts afresh at exec, so unlike the kernel's rusage it doesn't include whatever the judge was using when it spawned the candidate
+fn peak_memory(pid: u32) -> Option<u64> {
+    let status = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kilobytes: u64 = line.trim_start_matches("VmHWM:").trim().trim_end_matches("kB").trim().parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Verdict::Accepted { time, memory } => {
+                write!(f, "accepted in {:.3}s", time.as_secs_f64())?;
+                match memory {
+                    Some(memory) => writeln!(f, ", peak memory {} KiB", memory / 1024),
+                    None => writeln!(f, ", peak memory unknown"),
+                }
+            },
+            Verdict::WrongAnswer(mismatch) => {
+                //the query file has the count on its first line, so query n is on line n + 2
+                writeln!(f, "wrong answer on query {} (line {} of the input)", mismatch.index + 1, mismatch.index + 2)?;
+                for (index, query) in &mismatch.context {
+                    let marker = if *index == mismatch.index { ">" } else { " " };
+                    writeln!(f, "{} {:>8}: {}", marker, index + 1, query)?;
+                }
+                writeln!(f, "expected: {}", mismatch.expected)?;
+                match &mismatch.found {
+                    Some(found) => writeln!(f, "found:    {}", found),
+                    None => writeln!(f, "found:    end of output"),
+                }
+            },
+            Verdict::TimeLimitExceeded { limit } => writeln!(f, "time limit of {:.3}s exceeded", limit.as_secs_f64()),
+            Verdict::MemoryLimitExceeded { limit, used } => {
+                writeln!(f, "memory limit of {} KiB exceeded, used {} KiB", limit / 1024, used / 1024)
+            },
+            Verdict::RuntimeError { status } => writeln!(f, "runtime error, candidate {}", status),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queries() -> Vec<Query> {
+        vec![
+            Query::Add(vec!["milk".to_string()], vec![]),
+            Query::Add(vec!["bread".to_string()], vec![]),
+            Query::Done(0),
+            Query::Search(vec![WordOrTag::Word("mk".to_string())]),
+        ]
+    }
+
+    #[test]
+    fn compare_test() {
+        let queries = queries();
+        let expected: Vec<String> = vec!["0", "1", "ok", "0"].into_iter().map(str::to_owned).collect();
+        assert_eq!(compare(&queries, expected.clone(), "0\n1\nok  \n0\n"), None);
+
+        let mismatch = compare(&queries, expected.clone(), "0\n1\nok\n1 0\n").unwrap();
+        assert_eq!(mismatch.index, 3);
+        assert_eq!(mismatch.context.len(), 4);
+        assert_eq!(mismatch.found, Some("1 0".to_owned()));
+
+        let mismatch = compare(&queries, expected.clone(), "0\n1\n").unwrap();
+        assert_eq!((mismatch.index, mismatch.found), (2, None));
+
+        let mismatch = compare(&queries, expected.clone(), "0\n1\nok\n0\nextra\n").unwrap();
+        assert_eq!((mismatch.index, mismatch.found), (4, Some("extra".to_owned())));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn judge_test() {
+        let queries = queries();
+        let input = "4\nadd \"milk\"\nadd \"bread\"\ndone 0\nsearch mk\n";
+        let limits = Limits { time: Some(Duration::from_millis(500)), memory: None };
+
+        let correct = judge(Command::new("printf").arg("0\\n1\\nok\\n0\\n"), input, &queries, Matching::Subsequence, Folding::Exact, &limits).unwrap();
+        assert!(matches!(correct, Verdict::Accepted { .. }));
+        let echo = judge(&mut Command::new("cat"), input, &queries, Matching::Subsequence, Folding::Exact, &limits).unwrap();
+        assert!(matches!(echo, Verdict::WrongAnswer(Mismatch { index: 0, .. })));
+        let slow = judge(Command::new("sleep").arg("5"), input, &queries, Matching::Subsequence, Folding::Exact, &limits).unwrap();
+        assert!(matches!(slow, Verdict::TimeLimitExceeded { .. }));
+        let failing = judge(&mut Command::new("false"), input, &queries, Matching::Subsequence, Folding::Exact, &limits).unwrap();
+        assert!(matches!(failing, Verdict::RuntimeError { .. }));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn judge_memory_test() {
+        //the candidate is only charged for its own memory, however much the judge is holding when it spawns it
+        let held = vec![1u8; 256 * 1024 * 1024];
+        let queries = queries();
+        let input = "4\nadd \"milk\"\nadd \"bread\"\ndone 0\nsearch mk\n";
+        let limits = Limits { time: Some(Duration::from_secs(5)), memory: Some(32 * 1024 * 1024) };
+        let candidate = judge(Command::new("sh").args(["-c", "sleep 0.1; printf '0\\n1\\nok\\n0\\n'"]), input, &queries, Matching::Subsequence, Folding::Exact, &limits).unwrap();
+        assert!(matches!(candidate, Verdict::Accepted { memory: Some(memory), .. } if memory > 0 && memory < 32 * 1024 * 1024), "{}", candidate);
+        assert_eq!(held.iter().map(|&b| b as usize).sum::<usize>(), held.len());
+    }
+}
@@ -0,0 +1,36 @@
+//! Generates task list queries (add, done, search, edit, tag, untag, undo and list, optionally mixed with invalid lines)
+//! for benchmarking and testing task managers.
+//!
+//! ```
+//! use query_generator::Generator;
+//! use rand::SeedableRng;
+//! use rand_pcg::Pcg64;
+//!
+//! let mut rng = Pcg64::seed_from_u64(42);
+//! let mut generator = Generator::builder().words(1000).tags(100).history(1000).build(&mut rng).unwrap();
+//! for query in generator.queries(&mut rng).take(10) {
+//!     println!("{}", query);
+//! }
+//! ```
+
+pub mod types;
+pub mod generator;
+pub mod distribution;
+pub mod active;
+pub mod vocabulary;
+pub mod matching;
+pub mod oracle;
+pub mod parser;
+pub mod output;
+pub mod runner;
+pub mod judge;
+mod constants;
+
+pub use types::{Query, SearchExpr, WordOrTag};
+pub use generator::{Generator, GeneratorBuilder, Pool, QueryWeights, SearchWeights};
+pub use distribution::{Bound, Counts, Lengths, Popularity, Selectivity};
+pub use active::DonePolicy;
+pub use vocabulary::{Script, Vocabulary};
+pub use matching::{Folding, Matching};
+pub use oracle::Answers;
+pub use output::{Format, Metadata, QueryWriter};
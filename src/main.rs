@@ -1,48 +1,229 @@
-mod types;
-mod generator;
-mod constants;
+mod cli;
+
+use cli::*;
+use query_generator::{judge, oracle, parser, runner, vocabulary, Folding, Format, Generator, Matching, Metadata, Query, QueryWriter, Vocabulary};
 
 use rand::prelude::*;
+use rand_pcg::Pcg64;
 use std::env;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io;
+use std::process;
 
-/* examples (don't forget to use release build)
-benchmark_5m.txt 5000000 1000 100 1000
-benchmark_5k.txt 5000 100 50 100
-*/
-
-fn main() -> io::Result<()> {
+fn main() {
     //read args
-    let args: Vec<String> = env::args().collect();
-    let file_path = args[1].to_owned();
-    let wrap_err = |e| io::Error::new(io::ErrorKind::InvalidInput, e);
-    let query_num: usize = args[2].parse().map_err(wrap_err)?;
-    let word_num: usize = args[3].parse().map_err(wrap_err)?;
-    let tag_num: usize = args[4].parse().map_err(wrap_err)?;
-    let add_query_history_num: usize = args[5].parse().map_err(wrap_err)?;
-    
+    let command = match cli::parse_args(env::args().skip(1)) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            eprintln!("run with --help for usage");
+            process::exit(2);
+        },
+    };
+    let result = match command {
+        Command::Generate(options) => generate(*options),
+        Command::Expect(options) => expect(options),
+        Command::Run(options) => run(*options),
+        Command::Judge(options) => judge(options),
+        Command::Help => {
+            print!("{}", USAGE);
+            Ok(())
+        },
+    };
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn build_generator(options: &GenerateOptions) -> io::Result<(Generator, Pcg64)> {
+    //seed a portable rng so the same seed always regenerates the same file
+    let seed = options.seed.unwrap_or_else(|| {
+        let seed = thread_rng().gen();
+        println!("Using seed {}", seed);
+        seed
+    });
+    let mut rng = Pcg64::seed_from_u64(seed);
+
+    //load any word lists, keeping the script's built-in ones for anything not given
+    let mut vocabulary = Vocabulary::builtin(options.script);
+    let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+    if let Some(path) = &options.segment_file {
+        vocabulary = vocabulary.with_segments(vocabulary::read_segments(path)?)
+            .map_err(|e| invalid(format!("{}: {}", path.display(), e)))?;
+    }
+    if let Some(path) = &options.alphabet_file {
+        vocabulary = vocabulary.with_alphabet(vocabulary::read_alphabet(path)?)
+            .map_err(|e| invalid(format!("{}: {}", path.display(), e)))?;
+    }
+    if let Some(script) = options.tag_script {
+        vocabulary = vocabulary.with_tag_vocabulary(Vocabulary::builtin(script));
+    }
+
     //create generator
-    let mut rng = thread_rng();
+    let generator = Generator::builder()
+        .words(options.word_num)
+        .tags(options.tag_num)
+        .history(options.add_query_history_num)
+        .weights(options.weights)
+        .search_weights(options.search_weights)
+        .selectivity(options.selectivity)
+        .matching(options.matching)
+        .folding(options.folding)
+        .variants(options.variants)
+        .escapes(options.escapes)
+        .invalid(options.invalid)
+        .malformed(options.format == Format::Text)
+        .lengths(options.lengths.clone())
+        .done_policy(options.done_policy)
+        .vocabulary(vocabulary)
+        .word_popularity(options.word_popularity)
+        .tag_popularity(options.tag_popularity)
+        .build(&mut rng)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    Ok((generator, rng))
+}
+
+fn generate(options: GenerateOptions) -> io::Result<()> {
+    let (mut generator, mut rng) = build_generator(&options)?;
     let rng = &mut rng;
-    let mut generator = generator::Generator::new(rng, word_num, tag_num, add_query_history_num);
 
     //open file and output buffer
-    let file = File::create(file_path)?;
-    let mut writer = io::BufWriter::new(file);
+    let file = File::create(&options.output).map_err(|e| with_path(e, &options.output))?;
+    //done tasks are only kept for undos, so neither the writer nor the reference holds on to them without any
+    let undo = options.weights.undo > 0;
+    let mut writer = QueryWriter::new(io::BufWriter::new(file), options.format)
+        .with_matching(options.matching)
+        .with_folding(options.folding)
+        .with_undo(undo);
+    let metadata = Metadata {
+        format: options.format,
+        query_num: options.query_num,
+        matching: options.matching,
+        folding: options.folding,
+        invalid: options.invalid > 0.0 && options.format == Format::Text,
+    };
+    metadata.write(&options.output)?;
 
     //write number of queries as first line
-    writeln!(writer, "{}", query_num)?;
+    writer.write_header(options.query_num)?;
+
+    //if asked for, answer every query with the reference task list as it is generated
+    let mut expected = match &options.expected {
+        Some(path) => {
+            let file = File::create(path).map_err(|e| with_path(e, path))?;
+            Some((oracle::TaskList::with_matching(options.matching).with_folding(options.folding).with_undo(undo), io::BufWriter::new(file)))
+        },
+        None => None,
+    };
 
     println!("Ready to generate queries");
     //write queries out to specified file
-    for _ in 0..query_num {
-        let query = generator.get_query(rng); //only thing which will grow is array of "active" indices
-        writeln!(writer, "{}", query)?;
+    for query in generator.queries(rng).take(options.query_num) { //only things which will grow are the set of "active" ids and the reference task list
+        writer.write_query(&query)?;
+        if let Some((tasks, expected_writer)) = &mut expected {
+            writeln!(expected_writer, "{}", options.answers.shorten(tasks.apply(&query)))?;
+        }
     }
     writer.flush()?;
-    println!("Done - generated {} queries", query_num);
+    if let Some((_, expected_writer)) = &mut expected {
+        expected_writer.flush()?;
+    }
+    println!("Done - generated {} queries", options.query_num);
 
     Ok(())
 }
+
+//answers an existing query file with the reference task list
+fn expect(options: ExpectOptions) -> io::Result<()> {
+    let input = std::fs::read_to_string(&options.input).map_err(|e| with_path(e, &options.input))?;
+    let queries = parse_queries(&input, &options.input)?;
+    let (matching, folding) = semantics(options.matching, options.folding, &options.input)?;
+    let undo = queries.iter().any(|query| matches!(query, Query::Undo));
+    let mut tasks = oracle::TaskList::with_matching(matching).with_folding(folding).with_undo(undo);
+    let file = File::create(&options.expected).map_err(|e| with_path(e, &options.expected))?;
+    let mut writer = io::BufWriter::new(file);
+    for query in &queries {
+        writeln!(writer, "{}", options.answers.shorten(tasks.apply(query)))?;
+    }
+    writer.flush()?;
+    println!("Done - answered {} queries", queries.len());
+    Ok(())
+}
+
+//streams queries into a target program and reports how quickly it answers
+fn run(options: RunOptions) -> io::Result<()> {
+    let mut target = process::Command::new(&options.target[0]);
+    target.args(&options.target[1..]);
+    let report = match &options.input {
+        //the file is read a line at a time as the target answers, so it never has to fit in memory
+        Some(path) => {
+            let file = File::open(path).map_err(|e| with_path(e, path))?;
+            let queries = parser::QueryReader::new(io::BufReader::new(file), text_metadata(path)?.invalid).map_err(|e| with_path(e, path))?;
+            let query_num = queries.query_num();
+            runner::run(&mut target, query_num, queries.map(|query| query.map_err(|e| with_path(e, path))))
+        },
+        None => {
+            let (mut generator, mut rng) = build_generator(&options.generate)?;
+            let queries: Box<dyn Iterator<Item = io::Result<Query>>> = Box::new(generator.queries(&mut rng).map(Ok));
+            runner::run(&mut target, options.generate.query_num, queries)
+        },
+    }.map_err(|e| io::Error::new(e.kind(), format!("{}: {}", options.target[0], e)))?;
+    print!("{}", report);
+    Ok(())
+}
+
+//checks a candidate's answers to a query file, exiting with 1 unless they are all correct
+fn judge(options: JudgeOptions) -> io::Result<()> {
+    let input = std::fs::read_to_string(&options.input).map_err(|e| with_path(e, &options.input))?;
+    let queries = parse_queries(&input, &options.input)?;
+    let mut candidate = process::Command::new(&options.candidate[0]);
+    candidate.args(&options.candidate[1..]);
+    let limits = judge::Limits {
+        time: options.time_limit,
+        memory: options.memory_limit,
+    };
+    let (matching, folding) = semantics(options.matching, options.folding, &options.input)?;
+    let verdict = judge::judge(&mut candidate, &input, &queries, matching, folding, &limits)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", options.candidate[0], e)))?;
+    print!("{}", verdict);
+    if let judge::Verdict::Accepted { .. } = verdict {
+        Ok(())
+    }
+    else {
+        process::exit(1);
+    }
+}
+
+//parses a query file, keeping the lines which don't parse if it was generated with invalid input mixed in
+fn parse_queries(input: &str, path: &std::path::Path) -> io::Result<Vec<Query>> {
+    let queries = match text_metadata(path)?.invalid {
+        true => parser::parse_queries_with_invalid(input),
+        false => parser::parse_queries(input),
+    };
+    queries.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))
+}
+
+//the metadata of a query file which is to be answered, files without any are taken to be text
+fn text_metadata(path: &std::path::Path) -> io::Result<Metadata> {
+    let metadata = Metadata::read(path)?.unwrap_or_default();
+    if metadata.format != Format::Text {
+        let message = format!("{} is a {} file, only text files can be answered", path.display(), metadata.format);
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+    }
+    Ok(metadata)
+}
+
+//the matching and folding given on the command line, otherwise the ones the input was generated for
+fn semantics(matching: Option<Matching>, folding: Option<Folding>, input: &std::path::Path) -> io::Result<(Matching, Folding)> {
+    let metadata = match (matching, folding) {
+        (Some(_), Some(_)) => Metadata::default(),
+        _ => Metadata::read(input)?.unwrap_or_default(),
+    };
+    Ok((matching.unwrap_or(metadata.matching), folding.unwrap_or(metadata.folding)))
+}
+
+fn with_path(e: io::Error, path: &std::path::Path) -> io::Error {
+    io::Error::new(e.kind(), format!("{}: {}", path.display(), e))
+}
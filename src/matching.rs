@@ -0,0 +1,271 @@
+use std::borrow::Cow;
+use std::cmp;
+use std::fmt;
+use std::str::FromStr;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+//how a search term matches a word or tag
+//words are compared a grapheme cluster at a time, so an accented letter or an emoji sequence is one character
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Matching {
+    Exact,          //the whole word
+    Prefix,         //the start of the word
+    Substring,      //anywhere in the word
+    #[default]
+    Subsequence,    //the word with any characters deleted, so "mlk" matches "milk"
+    Typo(u32),      //the whole word with at most this many typos, each a character substituted, inserted, deleted or swapped with its neighbour,
+                    //the generator derives terms exactly this many typos away
+}
+
+impl Matching {
+    pub fn matches(&self, term: &str, word: &str) -> bool {
+        match self {
+            Matching::Exact => term == word,
+            Matching::Prefix if is_ascii(term, word) => word.starts_with(term),
+            Matching::Prefix => graphemes(word).starts_with(&graphemes(term)),
+            Matching::Substring if is_ascii(term, word) => word.contains(term),
+            Matching::Substring => {
+                let term = graphemes(term);
+                term.is_empty() || graphemes(word).windows(term.len()).any(|window| window == term.as_slice())
+            },
+            Matching::Subsequence => is_subsequence(term, word),
+            Matching::Typo(budget) => within_distance(term, word, *budget as usize),
+        }
+    }
+
+    //how many more characters than the longest word a term needs to be sure of matching nothing
+    pub fn slack(&self) -> usize {
+        match self {
+            Matching::Typo(budget) => *budget as usize + 1,
+            _ => 1,
+        }
+    }
+}
+
+impl FromStr for Matching {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exact" => Ok(Matching::Exact),
+            "prefix" => Ok(Matching::Prefix),
+            "substring" => Ok(Matching::Substring),
+            "subsequence" => Ok(Matching::Subsequence),
+            "typo" => Ok(Matching::Typo(1)),
+            _ => match s.strip_prefix("typo:") {
+                Some(budget) => match budget.parse() {
+                    Ok(0) | Err(_) => Err(format!("typo budget `{}` isn't a positive whole number", budget)),
+                    Ok(budget) => Ok(Matching::Typo(budget)),
+                },
+                None => Err(format!("unknown matching `{}`, expected exact, prefix, substring, subsequence, typo or typo:<budget>", s)),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Matching {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Matching::Exact => write!(f, "exact"),
+            Matching::Prefix => write!(f, "prefix"),
+            Matching::Substring => write!(f, "substring"),
+            Matching::Subsequence => write!(f, "subsequence"),
+            Matching::Typo(1) => write!(f, "typo"),
+            Matching::Typo(budget) => write!(f, "typo:{}", budget),
+        }
+    }
+}
+
+//what words, tags and search terms are compared as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Folding {
+    #[default]
+    Exact,      //exactly as written
+    Folded,     //ignoring case, accents and other combining marks, full-width forms and nfc or nfd normalisation, so "Ｃａｆｅ" is "café"
+}
+
+impl Folding {
+    //compatibility decomposition turns full-width letters into ascii and splits accents off their letters to be dropped,
+    //then lowercasing and composing again gives the same string for every variant the generator writes
+    pub fn fold<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        match self {
+            Folding::Folded if !s.bytes().all(|b| b.is_ascii_lowercase() || b == b'-') => {
+                let folded: String = s.nfkd().filter(|c| !is_combining_mark(*c)).flat_map(char::to_lowercase).collect();
+                Cow::Owned(folded.nfc().collect())
+            },
+            _ => Cow::Borrowed(s),
+        }
+    }
+}
+
+impl FromStr for Folding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exact" => Ok(Folding::Exact),
+            "folded" => Ok(Folding::Folded),
+            _ => Err(format!("unknown folding `{}`, expected exact or folded", s)),
+        }
+    }
+}
+
+impl fmt::Display for Folding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Folding::Exact => write!(f, "exact"),
+            Folding::Folded => write!(f, "folded"),
+        }
+    }
+}
+
+//a search term matches a word if it can be made by deleting characters from it (the inverse of random_subsequence)
+pub fn is_subsequence(term: &str, word: &str) -> bool {
+    if is_ascii(term, word) {
+        let mut chars = word.chars();
+        return term.chars().all(|c| chars.any(|w| w == c));
+    }
+    let mut graphemes = word.graphemes(true);
+    term.graphemes(true).all(|c| graphemes.any(|w| w == c))
+}
+
+//whether the damerau-levenshtein distance between a and b, counting substitutions, insertions, deletions
+//and swaps of neighbouring characters, is at most max
+//unlike the simpler optimal string alignment distance, characters can be edited again after a swap, so "ca" is two from "abc"
+//and any n typos made one after the other are never more than n apart
+pub fn within_distance(a: &str, b: &str, max: usize) -> bool {
+    if is_ascii(a, b) {
+        return a.len().abs_diff(b.len()) <= max && distance(a.as_bytes(), b.as_bytes()) <= max;
+    }
+    let (a, b) = (graphemes(a), graphemes(b));
+    a.len().abs_diff(b.len()) <= max && distance(&a, &b) <= max
+}
+
+//lowrance and wagner's algorithm, with the table shifted by one so row and column 0 can hold a distance larger than any real one
+fn distance<T: PartialEq + Copy>(a: &[T], b: &[T]) -> usize {
+    let infinity = a.len() + b.len();
+    let width = b.len() + 2;
+    let at = |i: usize, j: usize| i * width + j;
+    let mut table = vec![0; (a.len() + 2) * width];
+    table[0] = infinity;
+    for i in 0..=a.len() {
+        table[at(i + 1, 0)] = infinity;
+        table[at(i + 1, 1)] = i;
+    }
+    for j in 0..=b.len() {
+        table[at(0, j + 1)] = infinity;
+        table[at(1, j + 1)] = j;
+    }
+    let mut last_row: Vec<(T, usize)> = vec![]; //last row of a each character was seen in, words are short enough to search it
+    for i in 1..=a.len() {
+        let mut last_column = 0; //last column of b in this row whose character matched
+        for j in 1..=b.len() {
+            let swap_row = last_row.iter().find(|(c, _)| *c == b[j - 1]).map_or(0, |(_, row)| *row);
+            let swap_column = last_column;
+            let cost = match a[i - 1] == b[j - 1] {
+                true => {
+                    last_column = j;
+                    0
+                },
+                false => 1,
+            };
+            table[at(i + 1, j + 1)] = cmp::min(
+                cmp::min(table[at(i, j)] + cost, table[at(i + 1, j)] + 1),
+                cmp::min(
+                    table[at(i, j + 1)] + 1,
+                    table[at(swap_row, swap_column)] + (i - swap_row - 1) + 1 + (j - swap_column - 1),
+                ),
+            );
+        }
+        match last_row.iter_mut().find(|(c, _)| *c == a[i - 1]) {
+            Some((_, row)) => *row = i,
+            None => last_row.push((a[i - 1], i)),
+        }
+    }
+    table[at(a.len() + 1, b.len() + 1)]
+}
+
+//ascii strings are their own grapheme clusters, so they can skip splitting into them
+fn is_ascii(a: &str, b: &str) -> bool {
+    a.is_ascii() && b.is_ascii()
+}
+
+fn graphemes(s: &str) -> Vec<&str> {
+    s.graphemes(true).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matching_test() {
+        let cases = [
+            ("milk", [true, true, true, true, true]),
+            ("mil", [false, true, true, true, true]),
+            ("ilk", [false, false, true, true, true]),
+            ("mlk", [false, false, false, true, true]),
+            ("mikl", [false, false, false, false, true]),
+            ("mulk", [false, false, false, false, true]),
+            ("mk", [false, false, false, true, false]),
+        ];
+        let matchings = [Matching::Exact, Matching::Prefix, Matching::Substring, Matching::Subsequence, Matching::Typo(1)];
+        for (term, expected) in cases.iter() {
+            for (matching, expected) in matchings.iter().zip(expected.iter()) {
+                assert_eq!(matching.matches(term, "milk"), *expected, "{} {}", matching, term);
+            }
+        }
+        for matching in matchings.iter() {
+            assert_eq!(matching.to_string().parse(), Ok(*matching));
+        }
+        assert!("fuzzy".parse::<Matching>().is_err());
+        assert_eq!("typo:3".parse(), Ok(Matching::Typo(3)));
+        assert_eq!(Matching::Typo(3).to_string(), "typo:3");
+        assert!(Matching::Typo(2).matches("mkil", "milk"));
+        assert!("typo:0".parse::<Matching>().is_err());
+        assert!("typo:x".parse::<Matching>().is_err());
+    }
+
+    #[test]
+    fn folding_test() {
+        let folded = ["Café", "CAFÉ", "cafe\u{301}", "Ｃａｆé", "cAfE"];
+        for s in folded.iter() {
+            assert_eq!(Folding::Folded.fold(s), "cafe");
+            assert_eq!(Folding::Exact.fold(s), *s);
+        }
+        assert_eq!(Folding::Folded.fold("ЁЛКА-Ｘ"), "елка-x");
+        assert!(matches!(Folding::Folded.fold("oat-milk"), Cow::Borrowed(_)));
+        assert_eq!("folded".parse(), Ok(Folding::Folded));
+        assert!("lower".parse::<Folding>().is_err());
+    }
+
+    #[test]
+    fn subsequence_test() {
+        assert!(is_subsequence("mlk", "milk"));
+        assert!(is_subsequence("milk", "milk"));
+        assert!(!is_subsequence("klm", "milk"));
+        assert!(!is_subsequence("milks", "milk"));
+        assert!(is_subsequence("cfé", "café"));
+        assert!(!is_subsequence("cafe", "cafe\u{301}"));
+        assert!(!is_subsequence("👨", "👨‍👩‍👧"));
+        assert!(!Matching::Prefix.matches("👨", "👨‍👩‍👧"));
+        assert!(!Matching::Substring.matches("e", "cafe\u{301}"));
+        assert!(Matching::Substring.matches("fé", "café"));
+    }
+
+    #[test]
+    fn distance_test() {
+        assert!(within_distance("", "", 0));
+        assert!(within_distance("abcd", "acbd", 1));
+        assert!(!within_distance("abcd", "bdca", 2));
+        assert!(within_distance("kitten", "sitting", 3));
+        assert!(!within_distance("kitten", "sitting", 2));
+        assert!(within_distance("ünï", "üni", 1));
+        assert!(within_distance("e\u{301}a", "ae\u{301}", 1));
+        assert!(within_distance("👨‍👩‍👧", "👍🏽", 1));
+        assert!(within_distance("ca", "abc", 2));
+        assert!(!within_distance("ca", "abc", 1));
+    }
+}
@@ -0,0 +1,485 @@
+use crate::types::*;
+use crate::matching::{Folding, Matching};
+
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+use std::collections::{BTreeSet, HashMap};
+
+//reference model of a task list, used to work out what a correct implementation should answer for each query
+//ids are handed out in the same order as the generator hands them out, starting from 0
+pub struct TaskList {
+    next_id: u64,
+    tasks: HashMap<u64, Task>,
+    word_index: HashMap<String, BTreeSet<u64>>, //every active id containing each word
+    tag_index: HashMap<String, BTreeSet<u64>>,  //every active id containing each tag
+    done: Option<Vec<(u64, Task)>>,             //tasks marked done, most recent last, for undo to reopen, None when there are no undos
+    matching: Matching,                         //how search terms match words and tags
+    folding: Folding,                           //what everything is compared as, words and tags are kept folded
+}
+
+struct Task {
+    words: Vec<String>,
+    tags: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Answer {
+    Added(u64),
+    Done,
+    Reopened(u64),
+    Found(Vec<u64>),
+    Counted(usize),
+    Error(String),
+}
+
+//how much of each search and list answer is written: every matching id, or only how many there are
+//every id makes the answers grow with the number of active tasks as well as the number of queries,
+//so a large benchmark's answers can run to gigabytes, where counts alone stay about as big as the queries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Answers {
+    #[default]
+    Full,
+    Count,
+}
+
+impl Answers {
+    //the answer as written in this mode
+    pub fn shorten(self, answer: Answer) -> Answer {
+        match (self, answer) {
+            (Answers::Count, Answer::Found(ids)) => Answer::Counted(ids.len()),
+            (_, answer) => answer,
+        }
+    }
+}
+
+impl FromStr for Answers {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(Answers::Full),
+            "count" => Ok(Answers::Count),
+            _ => Err(format!("unknown answers `{}`, expected full or count", s)),
+        }
+    }
+}
+
+impl fmt::Display for Answers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Answers::Full => write!(f, "full"),
+            Answers::Count => write!(f, "count"),
+        }
+    }
+}
+
+//one line per query: the new id for add, the reopened id for undo, "ok" for done, edit, tag and untag,
+//and the number of matches followed by their ids for search and list, or only the number when counted
+//anything which can't be carried out is "error: " and why, such as a done of an inactive id, a search with no terms,
+//or for a line which doesn't parse the parser's error, which tells an unknown command, a missing quote and a bad id apart
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Answer::Added(id) => write!(f, "{}", id),
+            Answer::Done => write!(f, "ok"),
+            Answer::Reopened(id) => write!(f, "{}", id),
+            Answer::Found(ids) => {
+                write!(f, "{}", ids.len())?;
+                for id in ids {
+                    write!(f, " {}", id)?;
+                }
+                Ok(())
+            },
+            Answer::Counted(count) => write!(f, "{}", count),
+            Answer::Error(message) => write!(f, "error: {}", message),
+        }
+    }
+}
+
+impl Default for TaskList {
+    fn default() -> Self {
+        TaskList {
+            next_id: 0,
+            tasks: Default::default(),
+            word_index: Default::default(),
+            tag_index: Default::default(),
+            done: Some(vec![]),
+            matching: Default::default(),
+            folding: Default::default(),
+        }
+    }
+}
+
+impl TaskList {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_matching(matching: Matching) -> Self {
+        TaskList { matching, ..Default::default() }
+    }
+
+    pub fn with_folding(mut self, folding: Folding) -> Self {
+        self.folding = folding;
+        self
+    }
+
+    //whether undo can reopen tasks, without it every task marked done is dropped and undo has nothing to undo
+    //every done task is kept otherwise, so this is worth turning off when no undos will come
+    pub fn with_undo(mut self, undo: bool) -> Self {
+        self.done = if undo { Some(vec![]) } else { None };
+        self
+    }
+
+    pub fn matching(&self) -> Matching {
+        self.matching
+    }
+
+    pub fn apply(&mut self, query: &Query) -> Answer {
+        match query {
+            Query::Add(words, tags) => Answer::Added(self.add(words, tags)),
+            Query::Done(id) => ok_if_active(self.done(*id), *id),
+            Query::Search(terms) if terms.is_empty() => Answer::Error("empty search".to_owned()),
+            Query::Search(terms) => Answer::Found(self.search(terms)),
+            Query::BooleanSearch(expr) => Answer::Found(self.search_expr(expr)),
+            Query::Edit(id, words) => ok_if_active(self.edit(*id, words), *id),
+            Query::Tag(id, tags) => ok_if_active(self.tag(*id, tags), *id),
+            Query::Untag(id, tags) => ok_if_active(self.untag(*id, tags), *id),
+            Query::Undo => match self.undo() {
+                Some(id) => Answer::Reopened(id),
+                None => Answer::Error("nothing to undo".to_owned()),
+            },
+            Query::List => Answer::Found(self.search(&[])),
+            Query::Invalid(_, error) => Answer::Error(error.to_owned()),
+        }
+    }
+
+    pub fn add(&mut self, words: &[String], tags: &[String]) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        let task = Task { words: self.fold_all(words), tags: self.fold_all(tags) };
+        self.insert(id, task);
+        id
+    }
+
+    pub fn done(&mut self, id: u64) -> bool {
+        match self.tasks.remove(&id) {
+            Some(task) => {
+                unindex(&mut self.word_index, &task.words, id);
+                unindex(&mut self.tag_index, &task.tags, id);
+                if let Some(done) = &mut self.done {
+                    done.push((id, task));
+                }
+                true
+            },
+            None => false,
+        }
+    }
+
+    //reopens the task most recently marked done, returning its id
+    pub fn undo(&mut self) -> Option<u64> {
+        let (id, task) = self.done.as_mut()?.pop()?;
+        self.insert(id, task);
+        Some(id)
+    }
+
+    pub fn edit(&mut self, id: u64, words: &[String]) -> bool {
+        let words = self.fold_all(words);
+        match self.tasks.get_mut(&id) {
+            Some(task) => {
+                unindex(&mut self.word_index, &task.words, id);
+                task.words = words;
+                index(&mut self.word_index, &task.words, id);
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn tag(&mut self, id: u64, tags: &[String]) -> bool {
+        let tags = self.fold_all(tags);
+        match self.tasks.get_mut(&id) {
+            Some(task) => {
+                index(&mut self.tag_index, &tags, id);
+                task.tags.extend(tags);
+                true
+            },
+            None => false,
+        }
+    }
+
+    //removing a tag the task doesn't have is allowed and changes nothing
+    pub fn untag(&mut self, id: u64, tags: &[String]) -> bool {
+        let tags = self.fold_all(tags);
+        match self.tasks.get_mut(&id) {
+            Some(task) => {
+                unindex(&mut self.tag_index, &task.tags, id);
+                task.tags.retain(|tag| !tags.contains(tag));
+                index(&mut self.tag_index, &task.tags, id);
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn fold_all(&self, strings: &[String]) -> Vec<String> {
+        strings.iter().map(|s| self.folding.fold(s).into_owned()).collect()
+    }
+
+    fn insert(&mut self, id: u64, task: Task) {
+        index(&mut self.word_index, &task.words, id);
+        index(&mut self.tag_index, &task.tags, id);
+        self.tasks.insert(id, task);
+    }
+
+    //ids of every active task matched by all of the terms, in ascending order
+    pub fn search(&self, terms: &[WordOrTag]) -> Vec<u64> {
+        if terms.is_empty() {
+            let mut ids: Vec<u64> = self.tasks.keys().copied().collect();
+            ids.sort_unstable();
+            return ids;
+        }
+        let terms: Cow<[WordOrTag]> = match self.folding {
+            Folding::Exact => Cow::Borrowed(terms),
+            Folding::Folded => Cow::Owned(terms.iter().map(|term| fold_term(term, self.folding)).collect()),
+        };
+        //drive the search from the term with the fewest candidates, then check the rest against each candidate task
+        let driver = terms.iter()
+            .map(|term| self.postings(term))
+            .min_by_key(|postings| postings.iter().map(|ids| ids.len()).sum::<usize>())
+            .unwrap();
+        let mut candidates = BTreeSet::new();
+        for ids in driver {
+            candidates.extend(ids.iter().copied());
+        }
+        candidates.into_iter()
+            .filter(|id| {
+                let task = &self.tasks[id];
+                terms.iter().all(|term| term_matches(term, &task.words, &task.tags, self.matching))
+            })
+            .collect()
+    }
+
+    //ids of every active task matched by the expression, in ascending order
+    pub fn search_expr(&self, expr: &SearchExpr) -> Vec<u64> {
+        let expr: Cow<SearchExpr> = match self.folding {
+            Folding::Exact => Cow::Borrowed(expr),
+            Folding::Folded => Cow::Owned(fold_expr(expr, self.folding)),
+        };
+        let expr = expr.as_ref();
+        let matches = |id: &u64| {
+            let task = &self.tasks[id];
+            expr_matches(expr, &task.words, &task.tags, self.matching)
+        };
+        match self.candidates(expr) {
+            Some(candidates) => candidates.into_iter().filter(matches).collect(),
+            None => {
+                let mut ids: Vec<u64> = self.tasks.keys().copied().filter(matches).collect();
+                ids.sort_unstable();
+                ids
+            },
+        }
+    }
+
+    //a set of ids which includes every match of the expression, or None if that could be any task
+    fn candidates(&self, expr: &SearchExpr) -> Option<BTreeSet<u64>> {
+        match expr {
+            SearchExpr::Term(term) => Some(self.postings(term).into_iter().flatten().copied().collect()),
+            SearchExpr::Not(_) => None,
+            SearchExpr::And(exprs) => exprs.iter()
+                .filter_map(|expr| self.candidates(expr))
+                .min_by_key(|candidates| candidates.len()),
+            SearchExpr::Or(exprs) => exprs.iter()
+                .map(|expr| self.candidates(expr))
+                .try_fold(BTreeSet::new(), |mut all, candidates| {
+                    all.extend(candidates?);
+                    Some(all)
+                }),
+        }
+    }
+
+    fn postings(&self, term: &WordOrTag) -> Vec<&BTreeSet<u64>> {
+        let (index, term) = match term {
+            WordOrTag::Word(word) => (&self.word_index, word),
+            WordOrTag::Tag(tag) => (&self.tag_index, tag),
+        };
+        //exact matches can go straight to the one key, everything else has to check them all
+        if self.matching == Matching::Exact {
+            return index.get(term.as_str()).into_iter().collect();
+        }
+        index.iter()
+            .filter(|(key, _)| self.matching.matches(term, key))
+            .map(|(_, ids)| ids)
+            .collect()
+    }
+}
+
+pub fn term_matches(term: &WordOrTag, words: &[String], tags: &[String], matching: Matching) -> bool {
+    match term {
+        WordOrTag::Word(word) => words.iter().any(|w| matching.matches(word, w)),
+        WordOrTag::Tag(tag) => tags.iter().any(|t| matching.matches(tag, t)),
+    }
+}
+
+//whether a task with the given words and tags is matched by the expression
+pub fn expr_matches(expr: &SearchExpr, words: &[String], tags: &[String], matching: Matching) -> bool {
+    match expr {
+        SearchExpr::Term(term) => term_matches(term, words, tags, matching),
+        SearchExpr::Not(expr) => !expr_matches(expr, words, tags, matching),
+        SearchExpr::And(exprs) => exprs.iter().all(|expr| expr_matches(expr, words, tags, matching)),
+        SearchExpr::Or(exprs) => exprs.iter().any(|expr| expr_matches(expr, words, tags, matching)),
+    }
+}
+
+pub fn fold_term(term: &WordOrTag, folding: Folding) -> WordOrTag {
+    match term {
+        WordOrTag::Word(word) => WordOrTag::Word(folding.fold(word).into_owned()),
+        WordOrTag::Tag(tag) => WordOrTag::Tag(folding.fold(tag).into_owned()),
+    }
+}
+
+pub fn fold_expr(expr: &SearchExpr, folding: Folding) -> SearchExpr {
+    let fold_all = |exprs: &[SearchExpr]| exprs.iter().map(|expr| fold_expr(expr, folding)).collect();
+    match expr {
+        SearchExpr::Term(term) => SearchExpr::Term(fold_term(term, folding)),
+        SearchExpr::Not(expr) => SearchExpr::Not(Box::new(fold_expr(expr, folding))),
+        SearchExpr::And(exprs) => SearchExpr::And(fold_all(exprs)),
+        SearchExpr::Or(exprs) => SearchExpr::Or(fold_all(exprs)),
+    }
+}
+
+fn ok_if_active(active: bool, id: u64) -> Answer {
+    match active {
+        true => Answer::Done,
+        false => Answer::Error(format!("no active task with id {}", id)),
+    }
+}
+
+fn index(index: &mut HashMap<String, BTreeSet<u64>>, keys: &[String], id: u64) {
+    for key in keys {
+        index.entry(key.to_owned()).or_default().insert(id);
+    }
+}
+
+fn unindex(index: &mut HashMap<String, BTreeSet<u64>>, keys: &[String], id: u64) {
+    for key in keys {
+        if let Some(ids) = index.get_mut(key) {
+            ids.remove(&id);
+            if ids.is_empty() {
+                index.remove(key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add(words: &[&str], tags: &[&str]) -> Query {
+        Query::Add(
+            words.iter().map(|s| s.to_string()).collect(),
+            tags.iter().map(|s| s.to_string()).collect(),
+        )
+    }
+
+    #[test]
+    fn task_list_test() {
+        let mut tasks = TaskList::new();
+        assert_eq!(tasks.apply(&add(&["buy", "milk"], &["home"])), Answer::Added(0));
+        assert_eq!(tasks.apply(&add(&["write", "report"], &["work"])), Answer::Added(1));
+        assert_eq!(tasks.apply(&add(&["buy", "printer-ink"], &["work", "home"])), Answer::Added(2));
+
+        let search = Query::Search(vec![WordOrTag::Word("by".to_string()), WordOrTag::Tag("hm".to_string())]);
+        assert_eq!(tasks.apply(&search), Answer::Found(vec![0, 2]));
+        assert_eq!(tasks.apply(&Query::Done(0)), Answer::Done);
+        assert_eq!(tasks.apply(&search), Answer::Found(vec![2]));
+        assert_eq!(tasks.apply(&Query::Done(0)).to_string(), "error: no active task with id 0");
+        assert_eq!(tasks.apply(&Query::Search(vec![WordOrTag::Word("pi-k".to_string())])).to_string(), "1 2");
+        assert_eq!(tasks.apply(&Query::Search(vec![WordOrTag::Word("milk".to_string())])).to_string(), "0");
+
+        assert_eq!(tasks.apply(&Query::Edit(1, vec!["file".to_string(), "report".to_string()])), Answer::Done);
+        assert_eq!(tasks.apply(&Query::Tag(2, vec!["shop".to_string(), "home".to_string()])), Answer::Done);
+        assert_eq!(tasks.apply(&Query::Untag(2, vec!["home".to_string()])), Answer::Done);
+        assert_eq!(tasks.apply(&Query::Tag(0, vec!["shop".to_string()])).to_string(), "error: no active task with id 0");
+        assert_eq!(tasks.apply(&Query::Search(vec![WordOrTag::Tag("hm".to_string())])).to_string(), "0");
+        assert_eq!(tasks.apply(&Query::Search(vec![WordOrTag::Word("fl".to_string()), WordOrTag::Tag("wk".to_string())])).to_string(), "1 1");
+        assert_eq!(tasks.apply(&Query::Done(2)), Answer::Done);
+        assert_eq!(tasks.apply(&Query::List).to_string(), "1 1");
+        assert_eq!(tasks.apply(&Query::Undo).to_string(), "2");
+        assert_eq!(tasks.apply(&Query::Undo).to_string(), "0");
+        assert_eq!(tasks.apply(&Query::Undo).to_string(), "error: nothing to undo");
+        assert_eq!(tasks.apply(&Query::Search(vec![])).to_string(), "error: empty search");
+
+        //each kind of invalid input is answered with an error of its own
+        let lines = ["done 99", "done -1", "done 18446744073709551616", "remove 4", "add \"a b", "add a b\"", "add \"a b\" #c #", "search (milk |"];
+        let file: String = std::iter::once(lines.len().to_string()).chain(lines.iter().map(|line| line.to_string())).map(|line| line + "\n").collect();
+        let answers: Vec<String> = crate::parser::parse_queries_with_invalid(&file).unwrap().iter().map(|query| tasks.apply(query).to_string()).collect();
+        assert_eq!(answers, [
+            "error: no active task with id 99",
+            "error: task ids can't be negative, found `-1`",
+            "error: task id `18446744073709551616` is too big",
+            "error: unknown command `remove`",
+            "error: expected `\"` to end the description",
+            "error: expected `\"` to start the description",
+            "error: expected a tag after `#`",
+            "error: expected a term",
+        ]);
+        assert_eq!(tasks.apply(&Query::Search(vec![WordOrTag::Tag("hm".to_string())])).to_string(), "1 0");
+
+        let search = |line: &str| match line.parse::<Query>().unwrap() {
+            Query::BooleanSearch(expr) => tasks.search_expr(&expr),
+            query => panic!("{} isn't a boolean search", query),
+        };
+        assert_eq!(search("search (rep | ink) -#wk"), vec![]);
+        assert_eq!(search("search buy | file"), vec![0, 1, 2]);
+        assert_eq!(search("search -#shop"), vec![0, 1]);
+        assert_eq!(search("search -(by | #shop) #wk"), vec![1]);
+
+        let mut tasks = TaskList::new().with_undo(false);
+        tasks.apply(&add(&["buy", "milk"], &[]));
+        assert_eq!(tasks.apply(&Query::Done(0)), Answer::Done);
+        assert_eq!(tasks.apply(&Query::Undo).to_string(), "error: nothing to undo");
+        assert_eq!(tasks.apply(&Query::List).to_string(), "0");
+    }
+
+    #[test]
+    fn answers_test() {
+        let mut tasks = TaskList::new();
+        tasks.apply(&add(&["buy", "milk"], &[]));
+        tasks.apply(&add(&["buy", "bread"], &[]));
+        let count = |query: &Query, tasks: &mut TaskList| Answers::Count.shorten(tasks.apply(query)).to_string();
+        assert_eq!(count(&Query::Search(vec![WordOrTag::Word("buy".to_string())]), &mut tasks), "2");
+        assert_eq!(count(&Query::List, &mut tasks), "2");
+        assert_eq!(count(&Query::Done(1), &mut tasks), "ok");
+        assert_eq!(count(&Query::Undo, &mut tasks), "1");
+        assert_eq!(count(&Query::Search(vec![]), &mut tasks), "error: empty search");
+        assert_eq!(Answers::Full.shorten(tasks.apply(&Query::List)).to_string(), "2 0 1");
+        assert_eq!("count".parse(), Ok(Answers::Count));
+        assert_eq!(Answers::Full.to_string(), "full");
+        assert!("ids".parse::<Answers>().is_err());
+    }
+
+    #[test]
+    fn matching_task_list_test() {
+        let mut tasks = TaskList::with_matching(Matching::Prefix);
+        tasks.apply(&add(&["buy", "milk"], &["home"]));
+        tasks.apply(&add(&["buy", "oat-milk"], &["shop"]));
+        assert_eq!(tasks.apply(&Query::Search(vec![WordOrTag::Word("mi".to_string())])).to_string(), "1 0");
+        assert_eq!(tasks.apply(&Query::Search(vec![WordOrTag::Word("mlk".to_string())])).to_string(), "0");
+
+        let mut tasks = TaskList::with_matching(Matching::Exact);
+        tasks.apply(&add(&["buy", "milk"], &["home"]));
+        assert_eq!(tasks.apply(&Query::Search(vec![WordOrTag::Word("milk".to_string())])).to_string(), "1 0");
+        assert_eq!(tasks.apply(&Query::Search(vec![WordOrTag::Tag("hom".to_string())])).to_string(), "0");
+
+        let mut tasks = TaskList::with_matching(Matching::Prefix).with_folding(Folding::Folded);
+        tasks.apply(&add(&["Crème", "BRÛLÉE"], &["Ｄｅｓｓｅｒｔ"]));
+        assert_eq!(tasks.apply(&Query::Search(vec![WordOrTag::Word("CREM".to_string())])).to_string(), "1 0");
+        assert_eq!(tasks.apply(&"search brule\u{301} #dess".parse().unwrap()).to_string(), "1 0");
+        assert_eq!(tasks.apply(&"search -#DESSERT | crème".parse().unwrap()).to_string(), "1 0");
+        assert_eq!(tasks.apply(&Query::Untag(0, vec!["dessert".to_string()])), Answer::Done);
+        assert_eq!(tasks.apply(&Query::Search(vec![WordOrTag::Tag("d".to_string())])).to_string(), "0");
+    }
+}
@@ -0,0 +1,544 @@
+use crate::types::*;
+use crate::active::{ActiveIds, DonePolicy};
+use crate::matching::{Folding, Matching};
+
+use std::fmt;
+use std::io::prelude::*;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+//how queries are written out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    Text,   //the number of queries then one query per line, as written by Query's Display impl
+    Jsonl,  //one json object per line, with no count line
+    Sql,    //the schema below then one line of sql statements per query
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "jsonl" => Ok(Format::Jsonl),
+            "sql" => Ok(Format::Sql),
+            _ => Err(format!("unknown format `{}`, expected text, jsonl or sql", s)),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Format::Text => write!(f, "text"),
+            Format::Jsonl => write!(f, "jsonl"),
+            Format::Sql => write!(f, "sql"),
+        }
+    }
+}
+
+//tables the sql format writes to, written out at the start of every sql file
+//each task keeps its words and tags in order, the same tag can appear on a task more than once
+//done tasks are kept but flagged, and the ids are the ones the generator assigned, counting adds from 0
+pub const SQL_SCHEMA: &str = "\
+CREATE TABLE tasks (id BIGINT PRIMARY KEY, description TEXT NOT NULL, done BOOLEAN NOT NULL DEFAULT FALSE);
+CREATE TABLE task_words (task_id BIGINT NOT NULL REFERENCES tasks (id), position INTEGER NOT NULL, word TEXT NOT NULL, PRIMARY KEY (task_id, position));
+CREATE TABLE task_tags (task_id BIGINT NOT NULL REFERENCES tasks (id), position INTEGER NOT NULL, tag TEXT NOT NULL, PRIMARY KEY (task_id, position));
+";
+
+pub struct QueryWriter<W: Write> {
+    writer: W,
+    format: Format,
+    matching: Matching,         //how search terms match, which the sql format has to spell out
+    folding: Folding,           //whether they ignore case and accents, which the sql format has to spell out too
+    next_id: u64,               //id of the next add, which the sql format has to spell out
+    done_ids: Option<Vec<u64>>, //ids marked done, most recent last, which the sql format needs to spell out undos
+    active: ActiveIds,          //ids the sql format can change, anything else is an error in the reference and is written as a comment
+}
+
+impl<W: Write> QueryWriter<W> {
+    pub fn new(writer: W, format: Format) -> Self {
+        QueryWriter {
+            writer,
+            format,
+            matching: Default::default(),
+            folding: Default::default(),
+            next_id: 0,
+            done_ids: Some(vec![]),
+            active: ActiveIds::new(DonePolicy::Uniform),
+        }
+    }
+
+    pub fn with_matching(mut self, matching: Matching) -> Self {
+        self.matching = matching;
+        self
+    }
+
+    pub fn with_folding(mut self, folding: Folding) -> Self {
+        self.folding = folding;
+        self
+    }
+
+    //whether undos can come, without them the ids marked done aren't kept and any undo is written as having nothing to undo
+    pub fn with_undo(mut self, undo: bool) -> Self {
+        self.done_ids = if undo { Some(vec![]) } else { None };
+        self
+    }
+
+    //anything which comes before the queries themselves
+    pub fn write_header(&mut self, query_num: usize) -> io::Result<()> {
+        match self.format {
+            Format::Text => writeln!(self.writer, "{}", query_num),
+            Format::Jsonl => Ok(()),
+            Format::Sql => write!(self.writer, "-- {} queries, {} matching, {} folding\n{}", query_num, self.matching, self.folding, SQL_SCHEMA),
+        }
+    }
+
+    pub fn write_query(&mut self, query: &Query) -> io::Result<()> {
+        match self.format {
+            Format::Text => writeln!(self.writer, "{}", query),
+            Format::Jsonl => writeln!(self.writer, "{}", to_json(query)),
+            Format::Sql => {
+                let sql = self.sql_query(query);
+                writeln!(self.writer, "{}", sql)
+            },
+        }?;
+        if let Query::Add(..) = query {
+            self.next_id += 1;
+        }
+        Ok(())
+    }
+
+    //keeps track of which tasks are active, so only the dones the reference carries out are undone
+    fn sql_query(&mut self, query: &Query) -> String {
+        match query {
+            Query::Add(..) => {
+                self.active.insert(self.next_id);
+                to_sql(query, Some(self.next_id), self.matching, self.folding)
+            },
+            Query::Done(id) | Query::Edit(id, _) | Query::Tag(id, _) | Query::Untag(id, _) if !self.active.contains(*id) => {
+                format!("-- no active task with id {}", id)
+            },
+            Query::Done(id) => {
+                self.active.remove(*id);
+                if let Some(done_ids) = &mut self.done_ids {
+                    done_ids.push(*id);
+                }
+                to_sql(query, None, self.matching, self.folding)
+            },
+            Query::Undo => {
+                let id = self.done_ids.as_mut().and_then(Vec::pop);
+                if let Some(id) = id {
+                    self.active.insert(id);
+                }
+                to_sql(query, id, self.matching, self.folding)
+            },
+            _ => to_sql(query, None, self.matching, self.folding),
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+//{"op":"add","words":[...],"tags":[...]}, {"op":"done","id":N}, {"op":"search","terms":[{"word":...},{"tag":...}]},
+//{"op":"search","expr":E} where E is {"word":...}, {"tag":...}, {"not":E}, {"and":[E,...]} or {"or":[E,...]},
+//{"op":"edit","id":N,"words":[...]}, {"op":"tag","id":N,"tags":[...]}, {"op":"untag","id":N,"tags":[...]}, {"op":"undo"}, {"op":"list"},
+//or {"op":"invalid","line":...,"error":...} for a text line which doesn't parse
+pub fn to_json(query: &Query) -> String {
+    match query {
+        Query::Add(words, tags) => format!("{{\"op\":\"add\",\"words\":{},\"tags\":{}}}", json_strings(words), json_strings(tags)),
+        Query::Done(id) => format!("{{\"op\":\"done\",\"id\":{}}}", id),
+        Query::Search(params) => {
+            let terms: Vec<String> = params.iter().map(json_term).collect();
+            format!("{{\"op\":\"search\",\"terms\":[{}]}}", terms.join(","))
+        },
+        Query::BooleanSearch(expr) => format!("{{\"op\":\"search\",\"expr\":{}}}", json_expr(expr)),
+        Query::Edit(id, words) => format!("{{\"op\":\"edit\",\"id\":{},\"words\":{}}}", id, json_strings(words)),
+        Query::Tag(id, tags) | Query::Untag(id, tags) => {
+            format!("{{\"op\":\"{}\",\"id\":{},\"tags\":{}}}", query.command(), id, json_strings(tags))
+        },
+        Query::Undo | Query::List => format!("{{\"op\":\"{}\"}}", query.command()),
+        Query::Invalid(line, error) => format!("{{\"op\":\"invalid\",\"line\":{},\"error\":{}}}", json_string(line), json_string(error)),
+    }
+}
+
+//add inserts the task then its words and tags, done flags the task, search selects the ids of matching active tasks
+//search terms match as the matching says, so "mlk" becomes = 'mlk', LIKE 'mlk%', LIKE '%mlk%' or LIKE '%m%l%k%', with ! escaping any % and _ in the term
+//typo matching can't be written as a pattern, so it calls typo_distance(word, term), which the database has to provide,
+//giving the damerau-levenshtein distance as matching::within_distance counts it
+//LIKE compares characters rather than grapheme clusters, which only matters for words with combining accents or emoji sequences
+//folded terms are folded here and compared against fold(word), which the database has to provide, as matching::Folding::fold does,
+//untag compares folded tags the same way
+//exact folding compares as written, but LIKE is case insensitive for ascii in some databases, sqlite and mysql among them,
+//so once --variants writes words in mixed case prefix, substring and subsequence answers there can differ from the reference
+//unless case sensitive LIKE is switched on, such as with sqlite's PRAGMA case_sensitive_like
+//edit replaces the description and words, tag appends tags after the existing ones, untag deletes them and list selects every active id
+//id is the id of the next add, or for undo the id of the task it reopens, None if there's nothing to undo
+//queries the reference answers with an error are written as a comment, such as an empty search or a line which doesn't parse,
+//and QueryWriter does the same for ids which aren't active
+pub fn to_sql(query: &Query, id: Option<u64>, matching: Matching, folding: Folding) -> String {
+    match query {
+        Query::Add(words, tags) => {
+            let id = id.unwrap();
+            let mut sql = format!(
+                "INSERT INTO tasks (id, description, done) VALUES ({}, {}, FALSE);",
+                id, sql_string(&words.join(" ")),
+            );
+            if !words.is_empty() {
+                sql.push(' ');
+                sql.push_str(&insert_words(id, words));
+            }
+            if !tags.is_empty() {
+                let rows: Vec<String> = tags.iter().enumerate()
+                    .map(|(position, tag)| format!("({}, {}, {})", id, position, sql_string(tag)))
+                    .collect();
+                sql.push_str(&format!(" INSERT INTO task_tags (task_id, position, tag) VALUES {};", rows.join(", ")));
+            }
+            sql
+        },
+        Query::Done(id) => format!("UPDATE tasks SET done = TRUE WHERE id = {} AND NOT done;", id),
+        Query::Search(params) if params.is_empty() => "-- empty search".to_owned(),
+        Query::Search(params) => {
+            let mut sql = "SELECT t.id FROM tasks t WHERE NOT t.done".to_owned();
+            for param in params {
+                sql.push_str(" AND ");
+                sql.push_str(&sql_term(param, matching, folding));
+            }
+            sql.push_str(" ORDER BY t.id;");
+            sql
+        },
+        Query::BooleanSearch(expr) => format!("SELECT t.id FROM tasks t WHERE NOT t.done AND {} ORDER BY t.id;", sql_expr(expr, matching, folding)),
+        Query::Edit(id, words) => {
+            let mut sql = format!(
+                "UPDATE tasks SET description = {} WHERE id = {} AND NOT done; DELETE FROM task_words WHERE task_id = {};",
+                sql_string(&words.join(" ")), id, id,
+            );
+            if !words.is_empty() {
+                sql.push(' ');
+                sql.push_str(&insert_words(*id, words));
+            }
+            sql
+        },
+        Query::Tag(id, tags) => {
+            let inserts: Vec<String> = tags.iter()
+                .map(|tag| format!(
+                    "INSERT INTO task_tags (task_id, position, tag) SELECT {}, COALESCE(MAX(position) + 1, 0), {} FROM task_tags WHERE task_id = {};",
+                    id, sql_string(tag), id,
+                ))
+                .collect();
+            inserts.join(" ")
+        },
+        Query::Untag(id, tags) => {
+            let tags: Vec<String> = tags.iter().map(|tag| sql_string(&folding.fold(tag))).collect();
+            let column = match folding {
+                Folding::Exact => "tag",
+                Folding::Folded => "fold(tag)",
+            };
+            format!("DELETE FROM task_tags WHERE task_id = {} AND {} IN ({});", id, column, tags.join(", "))
+        },
+        Query::Undo => match id {
+            Some(id) => format!("UPDATE tasks SET done = FALSE WHERE id = {};", id),
+            None => "-- nothing to undo".to_owned(),
+        },
+        Query::List => "SELECT id FROM tasks WHERE NOT done ORDER BY id;".to_owned(),
+        Query::Invalid(line, error) => format!("-- {}: {}", error, line),
+    }
+}
+
+fn sql_term(term: &WordOrTag, matching: Matching, folding: Folding) -> String {
+    let (table, column, term) = match term {
+        WordOrTag::Word(word) => ("task_words", "word", word),
+        WordOrTag::Tag(tag) => ("task_tags", "tag", tag),
+    };
+    let column = match folding {
+        Folding::Exact => format!("m.{}", column),
+        Folding::Folded => format!("fold(m.{})", column),
+    };
+    let term = &*folding.fold(term);
+    let condition = match matching {
+        Matching::Exact => format!("{} = {}", column, sql_string(term)),
+        Matching::Prefix => format!("{} LIKE {} ESCAPE '!'", column, sql_string(&format!("{}%", like_escape(term)))),
+        Matching::Substring => format!("{} LIKE {} ESCAPE '!'", column, sql_string(&format!("%{}%", like_escape(term)))),
+        Matching::Subsequence => format!("{} LIKE {} ESCAPE '!'", column, sql_string(&subsequence_pattern(term))),
+        Matching::Typo(budget) => format!("typo_distance({}, {}) <= {}", column, sql_string(term), budget),
+    };
+    format!("EXISTS (SELECT 1 FROM {} m WHERE m.task_id = t.id AND {})", table, condition)
+}
+
+fn sql_expr(expr: &SearchExpr, matching: Matching, folding: Folding) -> String {
+    let join = |exprs: &[SearchExpr], operator: &str, empty: &str| match exprs.len() {
+        0 => empty.to_owned(),
+        _ => format!("({})", exprs.iter().map(|expr| sql_expr(expr, matching, folding)).collect::<Vec<_>>().join(operator)),
+    };
+    match expr {
+        SearchExpr::Term(term) => sql_term(term, matching, folding),
+        SearchExpr::Not(expr) => format!("NOT {}", sql_expr(expr, matching, folding)),
+        SearchExpr::And(exprs) => join(exprs, " AND ", "TRUE"),
+        SearchExpr::Or(exprs) => join(exprs, " OR ", "FALSE"),
+    }
+}
+
+fn insert_words(id: u64, words: &[String]) -> String {
+    let rows: Vec<String> = words.iter().enumerate()
+        .map(|(position, word)| format!("({}, {}, {})", id, position, sql_string(word)))
+        .collect();
+    format!("INSERT INTO task_words (task_id, position, word) VALUES {};", rows.join(", "))
+}
+
+fn subsequence_pattern(term: &str) -> String {
+    let mut pattern = String::from("%");
+    for c in term.chars() {
+        pattern.push_str(&like_escape(&c.to_string()));
+        pattern.push('%');
+    }
+    pattern
+}
+
+fn like_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c == '%' || c == '_' || c == '!' {
+            escaped.push('!');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+//what a query file was generated for, written alongside it as key=value lines so it can be answered the same way later
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Metadata {
+    pub format: Format,
+    pub query_num: usize,
+    pub matching: Matching,
+    pub folding: Folding,
+    pub invalid: bool,  //whether lines which don't parse were mixed in on purpose, to be answered as invalid queries
+}
+
+impl Metadata {
+    //the file the metadata for a query file is kept in, the query file's path with .meta on the end
+    pub fn path(output: &Path) -> PathBuf {
+        let mut path = output.as_os_str().to_owned();
+        path.push(".meta");
+        PathBuf::from(path)
+    }
+
+    //None if the query file has no metadata, as for files written by hand or by older versions
+    pub fn read(output: &Path) -> io::Result<Option<Metadata>> {
+        let path = Metadata::path(output);
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(io::Error::new(e.kind(), format!("{}: {}", path.display(), e))),
+        };
+        contents.parse()
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn write(&self, output: &Path) -> io::Result<()> {
+        let path = Metadata::path(output);
+        std::fs::write(&path, self.to_string()).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+    }
+}
+
+impl FromStr for Metadata {
+    type Err = String;
+
+    //unknown keys are skipped so newer files can still be read
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut metadata = Metadata::default();
+        for (i, line) in s.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let (key, value) = line.split_once('=').ok_or(format!("line {}: expected key=value, found `{}`", i + 1, line))?;
+            let invalid = |e: String| format!("line {}: invalid {} `{}`: {}", i + 1, key, value, e);
+            match key.trim() {
+                "format" => metadata.format = value.trim().parse().map_err(invalid)?,
+                "queries" => metadata.query_num = value.trim().parse().map_err(|e: std::num::ParseIntError| invalid(e.to_string()))?,
+                "matching" => metadata.matching = value.trim().parse().map_err(invalid)?,
+                "folding" => metadata.folding = value.trim().parse().map_err(invalid)?,
+                "invalid" => metadata.invalid = value.trim().parse().map_err(|e: std::str::ParseBoolError| invalid(e.to_string()))?,
+                _ => (),
+            }
+        }
+        Ok(metadata)
+    }
+}
+
+impl fmt::Display for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "format={}", self.format)?;
+        writeln!(f, "queries={}", self.query_num)?;
+        writeln!(f, "matching={}", self.matching)?;
+        writeln!(f, "folding={}", self.folding)?;
+        //for readers which don't parse the matching, terms derived from a word or tag are always exactly this many typos from it
+        if let Matching::Typo(budget) = self.matching {
+            writeln!(f, "typo_distance={}", budget)?;
+        }
+        writeln!(f, "invalid={}", self.invalid)
+    }
+}
+
+fn sql_string(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+fn json_term(term: &WordOrTag) -> String {
+    match term {
+        WordOrTag::Word(word) => format!("{{\"word\":{}}}", json_string(word)),
+        WordOrTag::Tag(tag) => format!("{{\"tag\":{}}}", json_string(tag)),
+    }
+}
+
+fn json_expr(expr: &SearchExpr) -> String {
+    let join = |exprs: &[SearchExpr]| exprs.iter().map(json_expr).collect::<Vec<_>>().join(",");
+    match expr {
+        SearchExpr::Term(term) => json_term(term),
+        SearchExpr::Not(expr) => format!("{{\"not\":{}}}", json_expr(expr)),
+        SearchExpr::And(exprs) => format!("{{\"and\":[{}]}}", join(exprs)),
+        SearchExpr::Or(exprs) => format!("{{\"or\":[{}]}}", join(exprs)),
+    }
+}
+
+fn json_strings(strings: &[String]) -> String {
+    let strings: Vec<String> = strings.iter().map(|s| json_string(s)).collect();
+    format!("[{}]", strings.join(","))
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_test() {
+        let add_query = Query::Add(
+            vec!["hello".to_string(), "wo\"rld".to_string()],
+            vec!["tag\\".to_string()],
+        );
+        let search_query = Query::Search(vec![
+            WordOrTag::Word("hello".to_string()),
+            WordOrTag::Tag("world".to_string()),
+        ]);
+        assert_eq!(to_json(&add_query), r#"{"op":"add","words":["hello","wo\"rld"],"tags":["tag\\"]}"#);
+        assert_eq!(to_json(&Query::Done(4)), r#"{"op":"done","id":4}"#);
+        assert_eq!(to_json(&search_query), r#"{"op":"search","terms":[{"word":"hello"},{"tag":"world"}]}"#);
+        assert_eq!(to_json(&Query::Add(vec![], vec![])), r#"{"op":"add","words":[],"tags":[]}"#);
+        assert_eq!(to_json(&Query::Untag(2, vec!["a".to_string()])), r#"{"op":"untag","id":2,"tags":["a"]}"#);
+        assert_eq!(to_json(&Query::Undo), r#"{"op":"undo"}"#);
+        assert_eq!(to_json(&Query::Invalid("done -1".to_string(), "bad".to_string())), r#"{"op":"invalid","line":"done -1","error":"bad"}"#);
+        assert_eq!(
+            to_json(&"search (a | #b) -c".parse().unwrap()),
+            r#"{"op":"search","expr":{"and":[{"or":[{"word":"a"},{"tag":"b"}]},{"not":{"word":"c"}}]}}"#,
+        );
+        assert_eq!(json_string("\t\u{1}"), r#""\t\u0001""#);
+
+        let mut writer = QueryWriter::new(vec![], Format::Jsonl);
+        writer.write_header(1).unwrap();
+        writer.write_query(&Query::Done(4)).unwrap();
+        assert_eq!(String::from_utf8(writer.writer).unwrap(), "{\"op\":\"done\",\"id\":4}\n");
+    }
+
+    #[test]
+    fn sql_test() {
+        let add_query = Query::Add(
+            vec!["buy".to_string(), "o'clock".to_string()],
+            vec!["home".to_string()],
+        );
+        let search_query = Query::Search(vec![
+            WordOrTag::Word("b_y".to_string()),
+            WordOrTag::Tag("hm".to_string()),
+        ]);
+        assert_eq!(to_sql(&add_query, Some(3), Matching::Subsequence, Folding::Exact), "INSERT INTO tasks (id, description, done) VALUES (3, 'buy o''clock', FALSE); \
+            INSERT INTO task_words (task_id, position, word) VALUES (3, 0, 'buy'), (3, 1, 'o''clock'); \
+            INSERT INTO task_tags (task_id, position, tag) VALUES (3, 0, 'home');");
+        assert_eq!(to_sql(&Query::Done(4), None, Matching::Subsequence, Folding::Exact), "UPDATE tasks SET done = TRUE WHERE id = 4 AND NOT done;");
+        assert_eq!(to_sql(&search_query, None, Matching::Subsequence, Folding::Exact), "SELECT t.id FROM tasks t WHERE NOT t.done \
+            AND EXISTS (SELECT 1 FROM task_words m WHERE m.task_id = t.id AND m.word LIKE '%b%!_%y%' ESCAPE '!') \
+            AND EXISTS (SELECT 1 FROM task_tags m WHERE m.task_id = t.id AND m.tag LIKE '%h%m%' ESCAPE '!') \
+            ORDER BY t.id;");
+        assert_eq!(to_sql(&search_query, None, Matching::Prefix, Folding::Exact), "SELECT t.id FROM tasks t WHERE NOT t.done \
+            AND EXISTS (SELECT 1 FROM task_words m WHERE m.task_id = t.id AND m.word LIKE 'b!_y%' ESCAPE '!') \
+            AND EXISTS (SELECT 1 FROM task_tags m WHERE m.task_id = t.id AND m.tag LIKE 'hm%' ESCAPE '!') \
+            ORDER BY t.id;");
+        assert_eq!(to_sql(&search_query, None, Matching::Exact, Folding::Exact), "SELECT t.id FROM tasks t WHERE NOT t.done \
+            AND EXISTS (SELECT 1 FROM task_words m WHERE m.task_id = t.id AND m.word = 'b_y') \
+            AND EXISTS (SELECT 1 FROM task_tags m WHERE m.task_id = t.id AND m.tag = 'hm') \
+            ORDER BY t.id;");
+        assert_eq!(to_sql(&"search -a | #b".parse().unwrap(), None, Matching::Subsequence, Folding::Exact), "SELECT t.id FROM tasks t WHERE NOT t.done AND (\
+            (NOT EXISTS (SELECT 1 FROM task_words m WHERE m.task_id = t.id AND m.word LIKE '%a%' ESCAPE '!') \
+            OR EXISTS (SELECT 1 FROM task_tags m WHERE m.task_id = t.id AND m.tag LIKE '%b%' ESCAPE '!'))) ORDER BY t.id;");
+        assert_eq!(to_sql(&"search Ｍilk".parse().unwrap(), None, Matching::Exact, Folding::Folded), "SELECT t.id FROM tasks t WHERE NOT t.done \
+            AND EXISTS (SELECT 1 FROM task_words m WHERE m.task_id = t.id AND fold(m.word) = 'milk') ORDER BY t.id;");
+        assert_eq!(to_sql(&"untag 2 #Home".parse().unwrap(), None, Matching::Exact, Folding::Folded), "DELETE FROM task_tags WHERE task_id = 2 AND fold(tag) IN ('home');");
+
+        //ids count up with each add, whatever comes in between
+        let mut writer = QueryWriter::new(vec![], Format::Sql);
+        writer.write_query(&Query::Add(vec!["a".to_string()], vec![])).unwrap();
+        writer.write_query(&Query::Done(0)).unwrap();
+        writer.write_query(&Query::Add(vec!["b".to_string()], vec![])).unwrap();
+        writer.write_query(&Query::Undo).unwrap();
+        writer.write_query(&Query::Undo).unwrap();
+        let sql = String::from_utf8(writer.writer).unwrap();
+        let lines: Vec<&str> = sql.lines().collect();
+        assert!(lines[2].starts_with("INSERT INTO tasks (id, description, done) VALUES (1, 'b', FALSE);"));
+        assert_eq!(lines[3..], ["UPDATE tasks SET done = FALSE WHERE id = 0;", "-- nothing to undo"]);
+
+        //dones the reference rejects are never undone
+        let mut writer = QueryWriter::new(vec![], Format::Sql);
+        for query in [Query::Add(vec!["a".to_string()], vec![]), Query::Done(0), Query::Done(0), Query::Done(7), Query::Undo, Query::Undo, Query::Search(vec![])].iter() {
+            writer.write_query(query).unwrap();
+        }
+        let sql = String::from_utf8(writer.writer).unwrap();
+        let lines: Vec<&str> = sql.lines().collect();
+        assert_eq!(lines[2..], [
+            "-- no active task with id 0",
+            "-- no active task with id 7",
+            "UPDATE tasks SET done = FALSE WHERE id = 0;",
+            "-- nothing to undo",
+            "-- empty search",
+        ]);
+
+        //without undos nothing marked done is kept
+        let mut writer = QueryWriter::new(vec![], Format::Sql).with_undo(false);
+        writer.write_query(&Query::Add(vec!["a".to_string()], vec![])).unwrap();
+        writer.write_query(&Query::Done(0)).unwrap();
+        assert_eq!(writer.done_ids, None);
+    }
+
+    #[test]
+    fn metadata_test() {
+        let metadata = Metadata { format: Format::Sql, query_num: 10, matching: Matching::Prefix, folding: Folding::Folded, invalid: true };
+        assert_eq!(metadata.to_string(), "format=sql\nqueries=10\nmatching=prefix\nfolding=folded\ninvalid=true\n");
+        assert_eq!(metadata.to_string().parse(), Ok(metadata));
+        let metadata = Metadata { matching: Matching::Typo(2), ..Default::default() };
+        assert!(metadata.to_string().contains("\nmatching=typo:2\nfolding=exact\ntypo_distance=2\n"));
+        assert_eq!(metadata.to_string().parse(), Ok(metadata));
+        assert_eq!("matching=exact\nseed=4\n".parse(), Ok(Metadata { matching: Matching::Exact, ..Default::default() }));
+        assert!("matching=fuzzy".parse::<Metadata>().is_err());
+        assert!("invalid=sometimes".parse::<Metadata>().is_err());
+        assert!("matching".parse::<Metadata>().is_err());
+        assert_eq!(Metadata::path(Path::new("out/queries.txt")), PathBuf::from("out/queries.txt.meta"));
+    }
+}
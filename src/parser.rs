@@ -0,0 +1,492 @@
+use crate::types::*;
+
+use std::fmt;
+use std::error;
+use std::io::prelude::*;
+use std::io;
+use std::str::FromStr;
+
+//reads back the format written by the Display impls in types.rs
+//lines and columns are counted from 1, columns in characters
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl error::Error for ParseError {}
+
+impl FromStr for Query {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_query(s).map_err(|(offset, message)| error_at(1, s, offset, message))
+    }
+}
+
+impl FromStr for WordOrTag {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_term(s).map_err(|(offset, message)| error_at(1, s, offset, message))
+    }
+}
+
+//parses a whole file: the number of queries on the first line, then one query per line
+pub fn parse_queries(input: &str) -> Result<Vec<Query>, ParseError> {
+    parse_lines(input, false)
+}
+
+//parses a whole file like parse_queries, but keeps any line which doesn't parse as an invalid query rather than failing,
+//for files generated with invalid input mixed in
+pub fn parse_queries_with_invalid(input: &str) -> Result<Vec<Query>, ParseError> {
+    parse_lines(input, true)
+}
+
+fn parse_lines(input: &str, allow_invalid: bool) -> Result<Vec<Query>, ParseError> {
+    let mut lines = input.lines();
+    let query_num = parse_count(lines.next().unwrap_or(""))?;
+    let mut queries = Vec::with_capacity(query_num);
+    for (i, line) in lines.enumerate() {
+        let line_num = i + 2;
+        if queries.len() == query_num {
+            check_blank(line_num, line, query_num)?;
+            continue;
+        }
+        queries.push(parse_line(line_num, line, allow_invalid)?);
+    }
+    if queries.len() < query_num {
+        return Err(too_few(queries.len(), query_num));
+    }
+    Ok(queries)
+}
+
+//reads a query file a line at a time like parse_queries, or parse_queries_with_invalid if allow_invalid,
+//so a file can be streamed without holding it all in memory
+//errors are io::Errors, with the ParseError as the message for a line which doesn't parse
+pub struct QueryReader<R: BufRead> {
+    lines: io::Lines<R>,
+    query_num: usize,
+    read: usize,    //lines read after the count, which are all queries until there have been query_num
+    allow_invalid: bool,
+}
+
+impl<R: BufRead> QueryReader<R> {
+    //reads the count on the first line
+    pub fn new(reader: R, allow_invalid: bool) -> io::Result<Self> {
+        let mut lines = reader.lines();
+        let first = lines.next().transpose()?.unwrap_or_default();
+        let query_num = parse_count(&first).map_err(invalid_data)?;
+        Ok(QueryReader { lines, query_num, read: 0, allow_invalid })
+    }
+
+    pub fn query_num(&self) -> usize {
+        self.query_num
+    }
+}
+
+impl<R: BufRead> Iterator for QueryReader<R> {
+    type Item = io::Result<Query>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        //anything after the last query has to be blank, which is only checked if the reader is run past it
+        while self.read >= self.query_num {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
+            };
+            self.read += 1;
+            if let Err(e) = check_blank(self.read + 1, &line, self.query_num) {
+                return Some(Err(invalid_data(e)));
+            }
+        }
+        let line_num = self.read + 2;
+        let line = match self.lines.next() {
+            Some(Ok(line)) => line,
+            Some(Err(e)) => return Some(Err(e)),
+            None => {
+                let e = too_few(self.read, self.query_num);
+                self.read = usize::MAX;
+                return Some(Err(invalid_data(e)));
+            },
+        };
+        self.read += 1;
+        Some(parse_line(line_num, &line, self.allow_invalid).map_err(invalid_data))
+    }
+}
+
+fn parse_count(first: &str) -> Result<usize, ParseError> {
+    first.trim_end().parse().map_err(|_| error_at(1, first, 0, format!("expected the number of queries, found `{}`", first)))
+}
+
+fn parse_line(line_num: usize, line: &str, allow_invalid: bool) -> Result<Query, ParseError> {
+    match parse_query(line) {
+        Ok(query) => Ok(query),
+        Err((_, message)) if allow_invalid => Ok(Query::Invalid(line.to_owned(), message)),
+        Err((offset, message)) => Err(error_at(line_num, line, offset, message)),
+    }
+}
+
+fn check_blank(line_num: usize, line: &str, query_num: usize) -> Result<(), ParseError> {
+    match line.trim().is_empty() {
+        true => Ok(()),
+        false => Err(error_at(line_num, line, 0, format!("expected {} queries, found more", query_num))),
+    }
+}
+
+fn too_few(found: usize, query_num: usize) -> ParseError {
+    ParseError {
+        line: found + 2,
+        column: 1,
+        message: format!("expected {} queries, found {}", query_num, found),
+    }
+}
+
+fn invalid_data(e: ParseError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+fn error_at(line: usize, s: &str, offset: usize, message: String) -> ParseError {
+    ParseError {
+        line,
+        column: s[..offset].chars().count() + 1,
+        message,
+    }
+}
+
+//errors carry the byte offset they occurred at, which is turned into a column once the line is known
+type LineResult<T> = Result<T, (usize, String)>;
+
+fn parse_query(line: &str) -> LineResult<Query> {
+    let (command, rest) = match line.find(' ') {
+        Some(i) => (&line[..i], Some(i + 1)),
+        None => (line, None),
+    };
+    match command {
+        "add" => {
+            let start = rest.ok_or((line.len(), "expected a quoted description".to_owned()))?;
+            let (words, end) = parse_description(line, start)?;
+            if end < line.len() && !line[end..].starts_with(' ') {
+                return Err((end, "expected a space after the description".to_owned()));
+            }
+            Ok(Query::Add(words, parse_tags(line, end)?))
+        },
+        "done" => {
+            let start = rest.ok_or((line.len(), "expected a task id".to_owned()))?;
+            parse_digits(&line[start..])
+                .map(Query::Done)
+                .map_err(|message| (start, message))
+        },
+        "edit" => {
+            let (id, start) = parse_id(line, rest)?;
+            let start = start.ok_or((line.len(), "expected a quoted description".to_owned()))?;
+            let (words, end) = parse_description(line, start)?;
+            if let Some((offset, token)) = tokens(line, end).next() {
+                return Err((offset, format!("unexpected `{}` after the description", token)));
+            }
+            Ok(Query::Edit(id, words))
+        },
+        "tag" | "untag" => {
+            let (id, start) = parse_id(line, rest)?;
+            let tags = parse_tags(line, start.unwrap_or(line.len()))?;
+            if tags.is_empty() {
+                return Err((line.len(), "expected at least one tag".to_owned()));
+            }
+            match command {
+                "tag" => Ok(Query::Tag(id, tags)),
+                _ => Ok(Query::Untag(id, tags)),
+            }
+        },
+        "undo" | "list" => {
+            if let Some(start) = rest {
+                return Err((start, format!("unexpected `{}` after {}", &line[start..], command)));
+            }
+            match command {
+                "undo" => Ok(Query::Undo),
+                _ => Ok(Query::List),
+            }
+        },
+        "search" => {
+            let tokens = expr_tokens(line, rest.unwrap_or(line.len()));
+            let mut parser = ExprParser { tokens: &tokens, next: 0, end: line.len() };
+            let expr = parser.expr()?;
+            if let Some((offset, token)) = parser.peek() {
+                return Err((offset, format!("unexpected `{}`", token)));
+            }
+            let expr = match expr {
+                SearchExpr::And(_) => expr,
+                _ => SearchExpr::And(vec![expr]),
+            };
+            Ok(expr.into_query())
+        },
+        _ => Err((0, format!("unknown command `{}`", command))),
+    }
+}
+
+//a task id followed by a space or the end of the line, along with where anything after the space starts
+fn parse_id(line: &str, start: Option<usize>) -> LineResult<(u64, Option<usize>)> {
+    let start = start.ok_or((line.len(), "expected a task id".to_owned()))?;
+    let (id, rest) = match line[start..].find(' ') {
+        Some(i) => (&line[start..start + i], Some(start + i + 1)),
+        None => (&line[start..], None),
+    };
+    let id = parse_digits(id).map_err(|message| (start, message))?;
+    Ok((id, rest))
+}
+
+//a task id is only ever digits, so no sign, and must fit in a u64
+//negative and overflowing ids get errors of their own, as the reference tells them apart from anything else
+fn parse_digits(s: &str) -> Result<u64, String> {
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if s.strip_prefix('-').is_some_and(digits) {
+        return Err(format!("task ids can't be negative, found `{}`", s));
+    }
+    if !digits(s) {
+        return Err(format!("expected a task id, found `{}`", s));
+    }
+    s.parse().map_err(|_| format!("task id `{}` is too big", s))
+}
+
+//a quoted description starting at start, unescaped and split into words, along with the offset just after the closing quote
+//the escapes are the ones Display writes: \\, \", \n and \r
+fn parse_description(line: &str, start: usize) -> LineResult<(Vec<String>, usize)> {
+    if !line[start..].starts_with('"') {
+        return Err((start, "expected `\"` to start the description".to_owned()));
+    }
+    let mut description = String::new();
+    let mut chars = line[start + 1..].char_indices().map(|(i, c)| (start + 1 + i, c));
+    let end = loop {
+        match chars.next() {
+            Some((i, '"')) => break i,
+            Some((i, '\\')) => match chars.next() {
+                Some((_, '\\')) => description.push('\\'),
+                Some((_, '"')) => description.push('"'),
+                Some((_, 'n')) => description.push('\n'),
+                Some((_, 'r')) => description.push('\r'),
+                Some((_, c)) => return Err((i, format!("unknown escape `\\{}` in the description", c))),
+                None => return Err((line.len(), "expected `\"` to end the description".to_owned())),
+            },
+            Some((_, c)) => description.push(c),
+            None => return Err((line.len(), "expected `\"` to end the description".to_owned())),
+        }
+    };
+    let words = match description.is_empty() {
+        true => vec![],
+        false => description.split(' ').map(str::to_owned).collect(),
+    };
+    Ok((words, end + 1))
+}
+
+//space separated tags from start to the end of the line
+fn parse_tags(line: &str, start: usize) -> LineResult<Vec<String>> {
+    let mut tags = vec![];
+    for (offset, token) in tokens(line, start) {
+        match parse_term(token) {
+            Ok(WordOrTag::Tag(tag)) => tags.push(tag),
+            Ok(WordOrTag::Word(_)) => return Err((offset, format!("expected a tag starting with `#`, found `{}`", token))),
+            Err((i, message)) => return Err((offset + i, message)),
+        }
+    }
+    Ok(tags)
+}
+
+fn parse_term(s: &str) -> LineResult<WordOrTag> {
+    if let Some(i) = s.find([' ', '"']) {
+        return Err((i, format!("unexpected `{}` in search term", &s[i..i + 1])));
+    }
+    match s.strip_prefix('#') {
+        Some("") => Err((1, "expected a tag after `#`".to_owned())),
+        Some(tag) => Ok(WordOrTag::Tag(tag.to_owned())),
+        None if s.is_empty() => Err((0, "expected a word".to_owned())),
+        None => Ok(WordOrTag::Word(s.to_owned())),
+    }
+}
+
+//splits a search expression into brackets, bars, minuses and terms, along with the byte offset of each
+//a minus is only an operator at the start of a term, elsewhere it's part of the term
+fn expr_tokens(line: &str, start: usize) -> Vec<(usize, &str)> {
+    let mut tokens = vec![];
+    let mut term_start = None;
+    for (i, c) in line[start..].char_indices().map(|(i, c)| (start + i, c)) {
+        match c {
+            ' ' | '(' | ')' | '|' => {
+                if let Some(term_start) = term_start.take() {
+                    tokens.push((term_start, &line[term_start..i]));
+                }
+                if c != ' ' {
+                    tokens.push((i, &line[i..i + 1]));
+                }
+            },
+            '-' if term_start.is_none() => tokens.push((i, &line[i..i + 1])),
+            _ => {
+                if term_start.is_none() {
+                    term_start = Some(i);
+                }
+            },
+        }
+    }
+    if let Some(term_start) = term_start {
+        tokens.push((term_start, &line[term_start..]));
+    }
+    tokens
+}
+
+//recursive descent over the grammar described with SearchExpr
+struct ExprParser<'a> {
+    tokens: &'a [(usize, &'a str)],
+    next: usize,
+    end: usize, //offset reported for anything missing at the end of the line
+}
+
+impl<'a> ExprParser<'a> {
+    fn peek(&self) -> Option<(usize, &'a str)> {
+        self.tokens.get(self.next).copied()
+    }
+
+    fn expr(&mut self) -> LineResult<SearchExpr> {
+        let mut alternatives = vec![self.and()?];
+        while let Some((_, "|")) = self.peek() {
+            self.next += 1;
+            alternatives.push(self.and()?);
+        }
+        Ok(match alternatives.len() {
+            1 => alternatives.pop().unwrap(),
+            _ => SearchExpr::Or(alternatives),
+        })
+    }
+
+    fn and(&mut self) -> LineResult<SearchExpr> {
+        let mut exprs = vec![];
+        while let Some((_, token)) = self.peek() {
+            if token == "|" || token == ")" {
+                break;
+            }
+            exprs.push(self.unary()?);
+        }
+        match exprs.len() {
+            0 => match self.peek() {
+                Some((offset, token)) => Err((offset, format!("expected a term, found `{}`", token))),
+                None if self.next > 0 => Err((self.end, "expected a term".to_owned())),
+                None => Ok(SearchExpr::And(exprs)),    //an empty search
+            },
+            1 => Ok(exprs.pop().unwrap()),
+            _ => Ok(SearchExpr::And(exprs)),
+        }
+    }
+
+    fn unary(&mut self) -> LineResult<SearchExpr> {
+        let (offset, token) = self.peek().ok_or((self.end, "expected a term".to_owned()))?;
+        self.next += 1;
+        match token {
+            "-" => Ok(SearchExpr::Not(Box::new(self.unary()?))),
+            "(" => {
+                let expr = self.expr()?;
+                match self.peek() {
+                    Some((_, ")")) => {
+                        self.next += 1;
+                        Ok(expr)
+                    },
+                    _ => Err((self.peek().map_or(self.end, |(offset, _)| offset), format!("expected `)` to close the `(` at column {}", offset + 1))),
+                }
+            },
+            _ => parse_term(token).map(SearchExpr::Term).map_err(|(i, message)| (offset + i, message)),
+        }
+    }
+}
+
+//space separated tokens of line from start onwards, along with the byte offset of each
+fn tokens(line: &str, start: usize) -> impl Iterator<Item = (usize, &str)> {
+    let mut offset = start;
+    line[start..].split(' ').filter_map(move |token| {
+        let token_offset = offset;
+        offset += token.len() + 1;
+        match token.is_empty() {
+            true => None,
+            false => Some((token_offset, token)),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(e: ParseError) -> (usize, usize) {
+        (e.line, e.column)
+    }
+
+    #[test]
+    fn parse_error_test() {
+        assert_eq!("remove 4".parse::<Query>().map_err(position), Err((1, 1)));
+        assert_eq!("done four".parse::<Query>().map_err(position), Err((1, 6)));
+        assert_eq!("done +4".parse::<Query>().map_err(position), Err((1, 6)));
+        assert_eq!("done 18446744073709551616".parse::<Query>().map_err(position), Err((1, 6)));
+        assert_eq!("tag +4 #a".parse::<Query>().map_err(position), Err((1, 5)));
+        assert_eq!("add \"x\"#t".parse::<Query>().map_err(position), Err((1, 8)));
+        assert_eq!("add \"x\"y #t".parse::<Query>().map_err(position), Err((1, 8)));
+        assert_eq!("add hello #world".parse::<Query>().map_err(position), Err((1, 5)));
+        assert_eq!("add \"hello".parse::<Query>().map_err(position), Err((1, 11)));
+        assert_eq!("add \"hello\\\"".parse::<Query>().map_err(position), Err((1, 13)));
+        assert_eq!("add \"hel\\lo\"".parse::<Query>().map_err(position), Err((1, 9)));
+        assert_eq!("add \"hello\" #a world".parse::<Query>().map_err(position), Err((1, 16)));
+        assert_eq!("search hello # world".parse::<Query>().map_err(position), Err((1, 15)));
+        assert_eq!("#".parse::<WordOrTag>().map_err(position), Err((1, 2)));
+        assert_eq!("edit x \"hello\"".parse::<Query>().map_err(position), Err((1, 6)));
+        assert_eq!("edit 4 \"hello\" #a".parse::<Query>().map_err(position), Err((1, 16)));
+        assert_eq!("tag 4".parse::<Query>().map_err(position), Err((1, 6)));
+        assert_eq!("untag 4 a".parse::<Query>().map_err(position), Err((1, 9)));
+        assert_eq!("undo 4".parse::<Query>().map_err(position), Err((1, 6)));
+        assert_eq!("search (a | b".parse::<Query>().map_err(position), Err((1, 14)));
+        assert_eq!("search a | ) b".parse::<Query>().map_err(position), Err((1, 12)));
+        assert_eq!("search a -".parse::<Query>().map_err(position), Err((1, 11)));
+        assert_eq!("search a b)".parse::<Query>().map_err(position), Err((1, 11)));
+
+        assert_eq!(parse_queries("2\ndone 1\nsearch ünï #\n").map_err(position), Err((3, 13)));
+        assert_eq!(parse_queries("3\ndone 1\ndone 2\n").map_err(position), Err((4, 1)));
+        assert_eq!(parse_queries("1\ndone 1\ndone 2\n").map_err(position), Err((3, 1)));
+        assert_eq!(parse_queries("done 1\n").map_err(position), Err((1, 1)));
+    }
+
+    #[test]
+    fn parse_queries_test() {
+        let queries = parse_queries("3\nadd \"a b\" #c\ndone 0\nsearch a #c\n").unwrap();
+        assert_eq!(queries, vec![
+            Query::Add(vec!["a".to_string(), "b".to_string()], vec!["c".to_string()]),
+            Query::Done(0),
+            Query::Search(vec![WordOrTag::Word("a".to_string()), WordOrTag::Tag("c".to_string())]),
+        ]);
+        assert_eq!(parse_queries("0\n").unwrap(), vec![]);
+
+        let queries = parse_queries_with_invalid("3\ndone -1\nsearch\nadd \"a b\n").unwrap();
+        assert_eq!(queries, vec![
+            Query::Invalid("done -1".to_string(), "task ids can't be negative, found `-1`".to_string()),
+            Query::Search(vec![]),
+            Query::Invalid("add \"a b".to_string(), "expected `\"` to end the description".to_string()),
+        ]);
+        assert!(parse_queries_with_invalid("2\ndone 1\n").is_err());
+    }
+
+    #[test]
+    fn query_reader_test() {
+        //reads the same queries as parse_queries, and fails in the same places
+        let input = "3\nadd \"a b\" #c\ndone 0\nsearch a #c\n\n";
+        let reader = QueryReader::new(input.as_bytes(), false).unwrap();
+        assert_eq!(reader.query_num(), 3);
+        assert_eq!(reader.collect::<io::Result<Vec<Query>>>().unwrap(), parse_queries(input).unwrap());
+
+        let reader = QueryReader::new("2\ndone -1\nsearch\n".as_bytes(), true).unwrap();
+        assert_eq!(reader.collect::<io::Result<Vec<Query>>>().unwrap(), parse_queries_with_invalid("2\ndone -1\nsearch\n").unwrap());
+
+        let message = |input: &str| QueryReader::new(input.as_bytes(), false).and_then(|reader| reader.collect::<io::Result<Vec<Query>>>()).unwrap_err().to_string();
+        for input in ["done 1\n", "2\ndone 1\nsearch ünï #\n", "3\ndone 1\ndone 2\n", "1\ndone 1\ndone 2\n"].iter() {
+            assert_eq!(message(input), parse_queries(input).unwrap_err().to_string());
+        }
+    }
+}
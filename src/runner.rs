@@ -0,0 +1,166 @@
+use crate::types::*;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::prelude::*;
+use std::io;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+//how long a target whose pipes have failed is given to exit before it's killed
+const STOP_WAIT: Duration = Duration::from_millis(200);
+
+//feeds queries to a target program and times how long it takes to answer each one
+//the target is sent the number of queries then one query per line on stdin, as in the text format,
+//and must answer every query with exactly one line on stdout, flushed, before it is sent the next
+//if anything goes wrong partway through, the target is killed and waited for, and its exit status given with the error
+pub fn run<I: Iterator<Item = io::Result<Query>>>(target: &mut Command, query_num: usize, queries: I) -> io::Result<RunReport> {
+    let mut child = target
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdin = io::BufWriter::new(child.stdin.take().unwrap());
+    let mut stdout = io::BufReader::new(child.stdout.take().unwrap());
+
+    let mut report = RunReport::default();
+    let start = Instant::now();
+    if let Err(e) = exchange(&mut stdin, &mut stdout, query_num, queries, &mut report) {
+        drop(stdin);
+        return Err(match stop(&mut child) {
+            Ok(status) => io::Error::new(e.kind(), format!("{}, target {}", e, status)),
+            Err(_) => e,
+        });
+    }
+    drop(stdin);
+    let status = child.wait()?;
+    report.total = start.elapsed();
+    if !status.success() {
+        return Err(io::Error::other(format!("target exited with {}", status)));
+    }
+    Ok(report)
+}
+
+//gives the target a moment to exit by itself, as it usually is when its pipes fail, before killing it
+fn stop(child: &mut Child) -> io::Result<ExitStatus> {
+    let start = Instant::now();
+    while start.elapsed() < STOP_WAIT {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    let _ = child.kill();
+    child.wait()
+}
+
+//sends each query and reads its answer, recording how long each took
+fn exchange<W: Write, R: BufRead, I: Iterator<Item = io::Result<Query>>>(stdin: &mut W, stdout: &mut R, query_num: usize, queries: I, report: &mut RunReport) -> io::Result<()> {
+    let mut response = String::new();
+    writeln!(stdin, "{}", query_num)?;
+    for query in queries.take(query_num) {
+        let query = query?;
+        let sent = Instant::now();
+        writeln!(stdin, "{}", query)?;
+        stdin.flush()?;
+        response.clear();
+        if stdout.read_line(&mut response)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("target stopped answering after {} of {} queries", report.query_num, query_num),
+            ));
+        }
+        report.latencies.entry(query.command()).or_default().push(sent.elapsed());
+        report.query_num += 1;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct RunReport {
+    pub query_num: usize,
+    pub total: Duration,
+    pub latencies: BTreeMap<&'static str, Vec<Duration>>,  //time from sending each query to reading its answer, by command
+}
+
+impl RunReport {
+    pub fn throughput(&self) -> f64 {
+        self.query_num as f64 / self.total.as_secs_f64()
+    }
+}
+
+impl fmt::Display for RunReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} queries in {:.3}s ({:.0} queries/sec)", self.query_num, self.total.as_secs_f64(), self.throughput())?;
+        writeln!(f, "{:<8} {:>10} {:>10} {:>10} {:>10} {:>10}", "query", "count", "p50", "p90", "p99", "max")?;
+        for (command, latencies) in &self.latencies {
+            let mut latencies = latencies.clone();
+            latencies.sort_unstable();
+            writeln!(
+                f, "{:<8} {:>10} {:>10} {:>10} {:>10} {:>10}",
+                command,
+                latencies.len(),
+                micros(percentile(&latencies, 50.0)),
+                micros(percentile(&latencies, 90.0)),
+                micros(percentile(&latencies, 99.0)),
+                micros(percentile(&latencies, 100.0)),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+//nearest rank percentile of sorted latencies
+fn percentile(sorted: &[Duration], percent: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::default();
+    }
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn micros(duration: Duration) -> String {
+    format!("{}us", duration.as_micros())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_test() {
+        let latencies: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&latencies, 50.0), Duration::from_millis(50));
+        assert_eq!(percentile(&latencies, 99.0), Duration::from_millis(99));
+        assert_eq!(percentile(&latencies, 100.0), Duration::from_millis(100));
+        assert_eq!(percentile(&latencies[..1], 50.0), Duration::from_millis(1));
+        assert_eq!(percentile(&[], 50.0), Duration::default());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_test() {
+        //cat answers every line with itself, so each query gets one line back
+        let queries = vec![Query::Done(1), Query::Done(2), Query::Search(vec![])];
+        let report = run(&mut Command::new("cat"), 3, queries.into_iter().map(Ok)).unwrap();
+        assert_eq!(report.query_num, 3);
+        assert_eq!(report.latencies["done"].len(), 2);
+        assert_eq!(report.latencies["search"].len(), 1);
+
+        //head stops answering after the count line and first query
+        let queries = vec![Query::Done(1), Query::Done(2), Query::Done(3)];
+        let e = run(Command::new("head").arg("-n2"), 3, queries.into_iter().map(Ok)).unwrap_err();
+        assert!(e.to_string().contains("exit status: 0"), "{}", e);
+
+        //a target which dies without reading gets a write error, and is still waited for
+        let queries = (0..100_000).map(|id| Ok(Query::Done(id)));
+        let e = run(Command::new("sh").args(["-c", "exit 3"]), 100_000, queries).unwrap_err();
+        assert!(e.to_string().contains("exit status: 3"), "{}", e);
+
+        //a query which can't be read stops the run
+        let queries = vec![Ok(Query::Done(1)), Err(io::Error::new(io::ErrorKind::InvalidData, "line 3: bad"))];
+        let e = run(&mut Command::new("cat"), 2, queries.into_iter()).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert!(e.to_string().starts_with("line 3: bad, target"), "{}", e);
+    }
+}
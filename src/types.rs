@@ -6,25 +6,20 @@ pub enum Query {
     Add(Vec<String>, Vec<String>),
     Done(u64),
     Search(Vec<WordOrTag>),
+    BooleanSearch(SearchExpr),  //a search using OR, NOT or parentheses, written with the same command as search
+    Edit(u64, Vec<String>),     //replaces the description of an active task, keeping its tags
+    Tag(u64, Vec<String>),      //adds tags to an active task
+    Untag(u64, Vec<String>),    //removes every copy of each tag from an active task
+    Undo,                       //reopens the task most recently marked done
+    List,                       //every active task
+    Invalid(String, String),    //a line which doesn't parse, written as it is, and the parser's error for it
 }
 
 impl fmt::Display for Query {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Query::Add(words, tags) => {
-                let mut description = String::new();
-                for word in words {
-                    description.push_str(word);
-                    description.push(' ');
-                }
-                description.pop();
-                let mut tag_string = String::new();
-                for tag in tags {
-                    tag_string.push(' ');
-                    tag_string.push('#');
-                    tag_string.push_str(tag);
-                }
-                write!(f, "add \"{}\"{}", description, tag_string)
+                write!(f, "add \"{}\"{}", description(words), tag_string(tags))
             },
             Query::Done(index) => {
                 write!(f, "done {}", index)
@@ -37,6 +32,76 @@ impl fmt::Display for Query {
                 }
                 write!(f, "search{}", search_string)
             },
+            Query::BooleanSearch(expr) => {
+                write!(f, "search {}", expr)
+            },
+            Query::Edit(index, words) => {
+                write!(f, "edit {} \"{}\"", index, description(words))
+            },
+            Query::Tag(index, tags) => {
+                write!(f, "tag {}{}", index, tag_string(tags))
+            },
+            Query::Untag(index, tags) => {
+                write!(f, "untag {}{}", index, tag_string(tags))
+            },
+            Query::Undo => {
+                write!(f, "undo")
+            },
+            Query::List => {
+                write!(f, "list")
+            },
+            Query::Invalid(line, _) => {
+                write!(f, "{}", line)
+            },
+        }
+    }
+}
+
+//the words with a space between each, escaped to go between quotes:
+//a backslash is written \\, a quote \", a newline \n and a carriage return \r, anything else as it is, tabs and #s included
+//words are only split on spaces, so leading, trailing or repeated spaces give empty words, and an empty description no words at all
+fn description(words: &[String]) -> String {
+    let mut description = String::new();
+    for word in words {
+        for c in word.chars() {
+            match c {
+                '\\' => description.push_str("\\\\"),
+                '"' => description.push_str("\\\""),
+                '\n' => description.push_str("\\n"),
+                '\r' => description.push_str("\\r"),
+                c => description.push(c),
+            }
+        }
+        description.push(' ');
+    }
+    description.pop();
+    description
+}
+
+//each tag with a space and a # before it
+fn tag_string(tags: &[String]) -> String {
+    let mut tag_string = String::new();
+    for tag in tags {
+        tag_string.push(' ');
+        tag_string.push('#');
+        tag_string.push_str(tag);
+    }
+    tag_string
+}
+
+impl Query {
+    //the command each query starts with in the text format
+    pub fn command(&self) -> &'static str {
+        match self {
+            Query::Add(..) => "add",
+            Query::Done(..) => "done",
+            Query::Search(..) | Query::BooleanSearch(..) => "search",
+            Query::Edit(..) => "edit",
+            Query::Tag(..) => "tag",
+            Query::Untag(..) => "untag",
+            Query::Undo => "undo",
+            Query::List => "list",
+            Query::Invalid(..) => "invalid",
         }
     }
 }
@@ -66,6 +131,73 @@ impl fmt::Display for WordOrTag {
     }
 }
 
+//a search expression, written with this grammar, lowest precedence first:
+//    expr  = and ("|" and)*        any of the alternatives
+//    and   = unary (" " unary)*    all of them
+//    unary = "-" unary | "(" expr ")" | term
+//    term  = word | "#" tag
+//so "search (milk | bread) -#done" finds active tasks with a word matching milk or bread and no tag matching done
+//terms match as they do in a plain search, and no word term can start with "-"
+//expressions are kept in the form the parser gives: a top level And, with no And or Or of a single expression
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SearchExpr {
+    Term(WordOrTag),
+    Not(Box<SearchExpr>),
+    And(Vec<SearchExpr>),
+    Or(Vec<SearchExpr>),
+}
+
+impl SearchExpr {
+    //a plain search if the expression is just terms which all have to match
+    pub fn into_query(self) -> Query {
+        match self {
+            SearchExpr::And(exprs) if exprs.iter().all(|expr| matches!(expr, SearchExpr::Term(_))) => {
+                Query::Search(exprs.into_iter().map(|expr| match expr {
+                    SearchExpr::Term(term) => term,
+                    _ => unreachable!(),
+                }).collect())
+            },
+            expr => Query::BooleanSearch(expr),
+        }
+    }
+}
+
+impl fmt::Display for SearchExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SearchExpr::Term(term) => write!(f, "{}", term),
+            SearchExpr::Not(expr) => match **expr {
+                SearchExpr::Term(_) | SearchExpr::Not(_) => write!(f, "-{}", expr),
+                _ => write!(f, "-({})", expr),
+            },
+            SearchExpr::And(exprs) => {
+                for (i, expr) in exprs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    match expr {
+                        SearchExpr::And(_) | SearchExpr::Or(_) => write!(f, "({})", expr)?,
+                        _ => write!(f, "{}", expr)?,
+                    }
+                }
+                Ok(())
+            },
+            SearchExpr::Or(exprs) => {
+                for (i, expr) in exprs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " | ")?;
+                    }
+                    match expr {
+                        SearchExpr::Or(_) => write!(f, "({})", expr)?,
+                        _ => write!(f, "{}", expr)?,
+                    }
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -98,4 +230,48 @@ mod tests {
         assert_eq!(done_query.to_string(), "done 4".to_owned());
         assert_eq!(search_query.to_string(), "search hello #world bello #burld".to_owned());
     }
+
+    #[test]
+    fn description_escape_test() {
+        //quotes, backslashes and line breaks are escaped, tabs, hashes and empty words aren't
+        let words = ["", "say", "\"hi\"", "C:\\", "#1\tand\n2", ""].iter().map(|s| s.to_string()).collect();
+        assert_eq!(Query::Edit(2, words).to_string(), "edit 2 \" say \\\"hi\\\" C:\\\\ #1\tand\\n2 \"");
+
+        let line = "add \" \\\"quoted\\\" #not-a-tag back\\\\slash\ttab  \" #tag";
+        let query: Query = line.parse().unwrap();
+        assert_eq!(query.to_string(), line);
+        assert_eq!(query.to_string().parse::<Query>().unwrap(), query);
+    }
+
+    #[test]
+    fn query_parse_test() {
+        let lines = [
+            "add \"hello world\" #these #are #the #tags",
+            "add \"hello\"",
+            "done 4",
+            "search hello #world bello #burld",
+            "search",
+            "edit 4 \"hello there\"",
+            "tag 4 #a #b",
+            "untag 4 #a",
+            "undo",
+            "list",
+            "search (milk | bread) -#done",
+            "search -(a b | #c) --d (e | f g)",
+        ];
+        for line in lines.iter() {
+            let query: Query = line.parse().unwrap();
+            assert_eq!(query.to_string(), line.to_owned());
+            assert_eq!(query.to_string().parse::<Query>().unwrap(), query);
+        }
+
+        assert_eq!("add \"hello world\" #these #are".parse::<Query>().unwrap(), Query::Add(
+            vec!["hello".to_string(), "world".to_string()],
+            vec!["these".to_string(), "are".to_string()],
+        ));
+        assert_eq!("done 4".parse::<Query>().unwrap(), Query::Done(4));
+        assert_eq!("tag 2 #a #b".parse::<Query>().unwrap(), Query::Tag(2, vec!["a".to_string(), "b".to_string()]));
+        assert_eq!("#world".parse::<WordOrTag>().unwrap(), WordOrTag::Tag("world".to_string()));
+        assert_eq!("bello".parse::<WordOrTag>().unwrap(), WordOrTag::Word("bello".to_string()));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,219 @@
+use crate::constants::*;
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use unicode_segmentation::UnicodeSegmentation;
+
+//one of the built-in vocabularies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Script {
+    #[default]
+    Ascii,      //english words and a-z
+    Latin,      //accented latin words and letters from across europe, turkey and vietnam
+    Cyrillic,   //russian and ukrainian words and letters
+    Cjk,        //chinese, japanese and korean words and characters
+    Emoji,      //emoji, including skin tones, flags and sequences joined with zero width joiners
+}
+
+impl FromStr for Script {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ascii" => Ok(Script::Ascii),
+            "latin" => Ok(Script::Latin),
+            "cyrillic" => Ok(Script::Cyrillic),
+            "cjk" => Ok(Script::Cjk),
+            "emoji" => Ok(Script::Emoji),
+            _ => Err(format!("unknown script `{}`, expected ascii, latin, cyrillic, cjk or emoji", s)),
+        }
+    }
+}
+
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Script::Ascii => write!(f, "ascii"),
+            Script::Latin => write!(f, "latin"),
+            Script::Cyrillic => write!(f, "cyrillic"),
+            Script::Cjk => write!(f, "cjk"),
+            Script::Emoji => write!(f, "emoji"),
+        }
+    }
+}
+
+//what pool entries and random search terms are built from
+//pool entries join segments with hyphens, random search terms are strings of alphabet entries, each a single grapheme cluster
+//tags can be given a vocabulary of their own, otherwise they're built from the same one as words
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vocabulary {
+    segments: Vec<String>,
+    alphabet: Vec<String>,
+    tags: Option<Box<Vocabulary>>,
+}
+
+impl Default for Vocabulary {
+    fn default() -> Self {
+        Vocabulary::builtin(Script::Ascii)
+    }
+}
+
+impl Vocabulary {
+    pub fn builtin(script: Script) -> Self {
+        let (segments, alphabet): (&[&str], Vec<String>) = match script {
+            Script::Ascii => (&SEGMENTS, CHARS.iter().map(|c| c.to_string()).collect()),
+            Script::Latin => (&LATIN_SEGMENTS, to_strings(&LATIN_ALPHABET)),
+            Script::Cyrillic => (&CYRILLIC_SEGMENTS, to_strings(&CYRILLIC_ALPHABET)),
+            Script::Cjk => (&CJK_SEGMENTS, to_strings(&CJK_ALPHABET)),
+            Script::Emoji => (&EMOJI_SEGMENTS, to_strings(&EMOJI_ALPHABET)),
+        };
+        Vocabulary { segments: to_strings(segments), alphabet, tags: None }
+    }
+
+    pub fn new(segments: Vec<String>, alphabet: Vec<String>) -> Result<Self, String> {
+        if segments.is_empty() {
+            return Err("the segment list is empty".to_owned());
+        }
+        if alphabet.is_empty() {
+            return Err("the alphabet is empty".to_owned());
+        }
+        //anything which would change how a query line is split up can't appear in words or tags,
+        //and words can't start with a hyphen, which would be read as a not in a search
+        if let Some(segment) = segments.iter().find(|segment| segment.is_empty() || segment.contains(is_reserved)) {
+            return Err(format!("segment `{}` is empty or contains whitespace, `\"`, `#`, `(`, `)` or `|`", segment));
+        }
+        if let Some(segment) = segments.iter().find(|segment| segment.starts_with('-')) {
+            return Err(format!("segment `{}` starts with `-`", segment));
+        }
+        //search terms are cut up and put back together a grapheme cluster at a time,
+        //so nothing can join onto the hyphens or alphabet entries it ends up next to
+        if let Some(segment) = segments.iter().find(|segment| !stays_apart("-", segment) || !stays_apart(segment, "-")) {
+            return Err(format!("segment `{}` starts or ends with something which joins onto a neighbouring hyphen", segment));
+        }
+        if let Some(c) = alphabet.iter().find(|c| c.contains(is_reserved)) {
+            return Err(format!("alphabet entry {:?} contains whitespace, `\"`, `#`, `(`, `)` or `|`", c));
+        }
+        if let Some(c) = alphabet.iter().find(|c| c.graphemes(true).count() != 1 || !stays_apart("a", c) || !stays_apart(c, "a") || !stays_apart(c, c)) {
+            return Err(format!("alphabet entry {:?} isn't a single grapheme cluster which stays apart from its neighbours", c));
+        }
+        if alphabet.iter().all(|c| c == "-") {
+            return Err("the alphabet needs a character other than `-` to start search terms with".to_owned());
+        }
+        Ok(Vocabulary { segments, alphabet, tags: None })
+    }
+
+    pub fn segments(&self) -> &[String] {
+        &self.segments
+    }
+
+    pub fn alphabet(&self) -> &[String] {
+        &self.alphabet
+    }
+
+    //the vocabulary tags are built from
+    pub fn tags(&self) -> &Vocabulary {
+        self.tags.as_deref().unwrap_or(self)
+    }
+
+    pub fn with_segments(self, segments: Vec<String>) -> Result<Self, String> {
+        let tags = self.tags;
+        Ok(Vocabulary { tags, ..Vocabulary::new(segments, self.alphabet)? })
+    }
+
+    pub fn with_alphabet(self, alphabet: Vec<String>) -> Result<Self, String> {
+        let tags = self.tags;
+        Ok(Vocabulary { tags, ..Vocabulary::new(self.segments, alphabet)? })
+    }
+
+    pub fn with_tag_vocabulary(mut self, tags: Vocabulary) -> Self {
+        self.tags = Some(Box::new(Vocabulary { tags: None, ..tags }));
+        self
+    }
+}
+
+fn to_strings(strs: &[&str]) -> Vec<String> {
+    strs.iter().map(|s| s.to_string()).collect()
+}
+
+fn is_reserved(c: char) -> bool {
+    c.is_whitespace() || ['"', '#', '(', ')', '|'].contains(&c)
+}
+
+//whether a and b are still separate grapheme clusters when put side by side
+fn stays_apart(a: &str, b: &str) -> bool {
+    format!("{}{}", a, b).graphemes(true).count() == a.graphemes(true).count() + b.graphemes(true).count()
+}
+
+//one segment per line, blank lines are skipped
+pub fn read_segments(path: &Path) -> io::Result<Vec<String>> {
+    Ok(read_lines(path)?.into_iter().map(|(_, line)| line).collect())
+}
+
+//one grapheme cluster per line, such as a letter, a letter with combining accents or an emoji, blank lines are skipped
+pub fn read_alphabet(path: &Path) -> io::Result<Vec<String>> {
+    read_lines(path)?.into_iter()
+        .map(|(line_num, line)| match line.graphemes(true).count() {
+            1 => Ok(line),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: line {}: expected a single character, found `{}`", path.display(), line_num, line),
+            )),
+        })
+        .collect()
+}
+
+fn read_lines(path: &Path) -> io::Result<Vec<(usize, String)>> {
+    let contents = fs::read_to_string(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+    Ok(contents.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim().to_owned()))
+        .filter(|(_, line)| !line.is_empty())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vocabulary_test() {
+        let vocabulary = Vocabulary::default();
+        assert_eq!(vocabulary.segments().len(), 1000);
+        assert_eq!(vocabulary.alphabet().len(), 27);
+
+        let vocabulary = |segment: &str, c: &str| Vocabulary::new(vec![segment.to_string()], vec![c.to_string()]);
+        assert!(vocabulary("milk", "m").is_ok());
+        assert!(Vocabulary::new(vec![], vec!["m".to_string()]).is_err());
+        assert!(Vocabulary::new(vec!["milk".to_string()], vec![]).is_err());
+        assert!(vocabulary("oat milk", "m").is_err());
+        assert!(vocabulary("#milk", "m").is_err());
+        assert!(vocabulary("milk", "\"").is_err());
+        assert!(vocabulary("-milk", "m").is_err());
+        assert!(vocabulary("milk", "-").is_err());
+        assert!(vocabulary("milk|bread", "m").is_err());
+        assert!(vocabulary("milk", "mi").is_err());
+        assert!(vocabulary("\u{301}milk", "m").is_err());
+        assert!(vocabulary("milk", "\u{301}").is_err());
+        assert!(vocabulary("milk", "\u{1F1EF}").is_err());
+        assert!(vocabulary("mi\u{301}lk", "e\u{301}").is_ok());
+        assert!(vocabulary("milk", "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}").is_ok());
+    }
+
+    #[test]
+    fn builtin_test() {
+        //every built-in vocabulary passes the same checks as one read from files
+        for script in ["ascii", "latin", "cyrillic", "cjk", "emoji"].iter() {
+            let script: Script = script.parse().unwrap();
+            assert_eq!(script.to_string().parse(), Ok(script));
+            let vocabulary = Vocabulary::builtin(script);
+            assert_eq!(Vocabulary::new(vocabulary.segments().to_vec(), vocabulary.alphabet().to_vec()), Ok(vocabulary));
+        }
+        let vocabulary = Vocabulary::default().with_tag_vocabulary(Vocabulary::builtin(Script::Emoji));
+        assert_eq!(vocabulary.tags().alphabet(), Vocabulary::builtin(Script::Emoji).alphabet());
+        assert_eq!(vocabulary.tags().tags().alphabet().len(), Vocabulary::builtin(Script::Emoji).alphabet().len());
+        assert_eq!(vocabulary.alphabet().len(), 27);
+    }
+}